@@ -3,25 +3,52 @@
 use crate::vimba_sys::*;
 use crate::feature::*;
 use crate::error::Error;
-use crate::vimba::VimbaContext;
+use crate::vimba::{VimbaContext, GLOBAL_HANDLE, CAMERA_INFO_SIZE};
 use crate::format::PixelFormat;
-use crate::util::pointer_to_str;
+#[cfg(feature = "genicam")]
+use crate::genicam;
+use crate::util::{pointer_to_str, pointer_to_option_str, intern};
 use crate::{Result, vmbcall};
-use std::mem;
+use std::ffi::CString;
+use std::{fmt, mem, ptr};
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::any::Any;
+use std::alloc::{self, Layout};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::time::{Duration, Instant};
 use std::sync::mpsc;
+use std::thread;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use bitflags::bitflags;
 use num_traits::FromPrimitive;
 
 
 
 const FRAME_SIZE: u32 = mem::size_of::<VmbFrame_t>() as u32;
+const DISCONNECT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const ACQUISITION_STOP_DELAY: Duration = Duration::from_millis(200);
+const DEFAULT_STREAM_BUFFERS: usize = 3;
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(10);
+const FPS_WINDOW: usize = 30;
+#[cfg(feature = "genicam")]
+const MEMORY_READ_CHUNK: usize = 512;
+const PACKET_SIZE_ADJUST_TIMEOUT: Duration = Duration::from_secs(5);
+const USER_SET_TIMEOUT: Duration = Duration::from_secs(10);
+const PERSIST_SETTINGS_SIZE: u32 = mem::size_of::<VmbFeaturePersistSettings_t>() as u32;
 
 
 
 bitflags! {
     #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct AccessMode: u32 {
         const NONE = VmbAccessModeType::VmbAccessModeNone;
         const FULL = VmbAccessModeType::VmbAccessModeFull;
@@ -31,9 +58,81 @@ bitflags! {
     }
 }
 
+impl AccessMode {
+    // A camera is opened with exactly one of these:
+    //   FULL   - reading and writing features, and streaming
+    //   READ   - only reading features
+    //   CONFIG - configuring a GigE camera's IP address
+    //   LITE   - reading and writing registers directly, without features
+    // NONE and combinations only make sense as the permitted modes in CameraInfo.
+    pub fn is_valid_for_open(&self) -> bool {
+        [Self::FULL, Self::READ, Self::CONFIG, Self::LITE].contains(self)
+    }
+
+    pub fn can_stream(&self) -> bool {
+        self.contains(Self::FULL)
+    }
+
+    pub fn can_write_features(&self) -> bool {
+        self.contains(Self::FULL)
+    }
+}
+
+
+
+// How many frames the camera acquires once streaming starts. Not to be confused
+// with AccessMode, which is about how the camera is opened.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AcquisitionMode {
+    #[default]
+    Continuous,
+    SingleFrame,
+    MultiFrame(u32)
+}
+
+impl AcquisitionMode {
+    fn feature_value(&self) -> &'static str {
+        match self {
+            Self::Continuous => "Continuous",
+            Self::SingleFrame => "SingleFrame",
+            Self::MultiFrame(_) => "MultiFrame"
+        }
+    }
+}
+
+// The modes of auto features like ExposureAuto and GainAuto
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AutoMode {
+    #[default]
+    Off,
+    // Adjusts once and then goes back to Off by itself
+    Once,
+    Continuous
+}
+
+impl AutoMode {
+    pub fn feature_value(&self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Once => "Once",
+            Self::Continuous => "Continuous"
+        }
+    }
+
+    pub fn from_feature_value(value: &str) -> Option<Self> {
+        match value {
+            "Off" => Some(Self::Off),
+            "Once" => Some(Self::Once),
+            "Continuous" => Some(Self::Continuous),
+            _ => None
+        }
+    }
+}
+
 
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CameraInfo {
     pub id: String,
     pub name: String,
@@ -44,20 +143,56 @@ pub struct CameraInfo {
 }
 
 impl CameraInfo {
+    // Only the id is sure to be there. Some transport layers leave the rest null,
+    // in which case they're empty.
     pub(crate) fn from_c_struct(info: VmbCameraInfo_t) -> Self {
+        let optional = |p| unsafe { pointer_to_option_str(p).unwrap_or_default().to_string() };
+
         Self {
             id: unsafe { pointer_to_str(info.cameraIdString).to_string() },
-            name: unsafe { pointer_to_str(info.cameraName).to_string() },
-            serial: unsafe { pointer_to_str(info.serialString).to_string() },
-            model_name: unsafe { pointer_to_str(info.modelName).to_string() },
-            interface_id: unsafe { pointer_to_str(info.interfaceIdString).to_string() },
+            name: optional(info.cameraName),
+            serial: optional(info.serialString),
+            model_name: optional(info.modelName),
+            interface_id: optional(info.interfaceIdString),
             access_mode: AccessMode::from_bits_truncate(info.permittedAccess)
         }
     }
+
+    // Vimba stops permitting full access while a camera is open elsewhere, so this
+    // says whether it could be opened for streaming right now
+    pub fn is_available(&self) -> bool {
+        self.access_mode.can_stream()
+    }
+}
+
+// A camera is identified by its id alone. The rest can change while it's the same
+// camera, e.g. the permitted access mode once another process opens it.
+impl PartialEq for CameraInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for CameraInfo {}
+
+impl Hash for CameraInfo {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl fmt::Display for CameraInfo {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            fmt, "{} (serial {}) on {} [id={}]",
+            self.model_name, self.serial, self.interface_id, self.id
+        )
+    }
 }
 
 
 
+#[derive(Clone)]
 pub struct Frame<T: AsRef<[u8]>> {
     pub data: T,
     pub width: usize,
@@ -66,7 +201,12 @@ pub struct Frame<T: AsRef<[u8]>> {
     pub offset_y: usize,
     pub id: u64,
     pub timestamp: u64,
-    pub format: PixelFormat
+    pub pixel_format: Option<PixelFormat>,
+    pub ancillary_size: usize,
+
+    // Needed to split the image from the ancillary data, and to hand the frame back
+    // to Vimba for reading chunk features
+    image_size: usize
 }
 
 impl<T: AsRef<[u8]>> Frame<T> {
@@ -79,8 +219,9 @@ impl<T: AsRef<[u8]>> Frame<T> {
             offset_y: frame.offsetY as usize,
             id: frame.frameID,
             timestamp: frame.timestamp,
-            format: PixelFormat::from_u32(frame.pixelFormat)
-                                .expect("Invalid pixelFormat in C struct")
+            pixel_format: PixelFormat::from_u32(frame.pixelFormat),
+            ancillary_size: frame.ancillarySize as usize,
+            image_size: frame.imageSize as usize
         }
     }
 
@@ -94,242 +235,2104 @@ impl<T: AsRef<[u8]>> Frame<T> {
             offset_y: self.offset_y,
             id: self.id,
             timestamp: self.timestamp,
-            format: self.format
+            pixel_format: self.pixel_format,
+            ancillary_size: self.ancillary_size,
+            image_size: self.image_size
+        }
+    }
+
+    pub fn with_ref_data(&self) -> Frame<&[u8]> {
+        self.map_data(AsRef::as_ref)
+    }
+
+    pub fn with_vec_data(&self) -> Frame<Vec<u8>> {
+        self.map_data(|data| data.as_ref().to_vec())
+    }
+
+    pub fn timestamp_duration(&self, freq_hz: u64) -> Duration {
+        if freq_hz == 0 { return Duration::ZERO }
+
+        let secs = self.timestamp/freq_hz;
+        let rem_ticks = (self.timestamp % freq_hz) as u128;
+        let nanos = rem_ticks*1_000_000_000/(freq_hz as u128);
+
+        Duration::new(secs, nanos as u32)
+    }
+
+    // The buffer can be bigger than the image, with ancillary data after it. Vimba
+    // normally says how big the image is, but failing that it's worked out from
+    // the format and size.
+    fn image_len(&self) -> usize {
+        let data_len = self.data.as_ref().len();
+        let len = match (self.image_size, self.pixel_format) {
+            (0, Some(format)) => format.image_buffer_size(self.width, self.height),
+            (0, None) => data_len.saturating_sub(self.ancillary_size),
+            (size, _) => size
+        };
+
+        len.min(data_len)
+    }
+
+    pub fn image_data(&self) -> &[u8] {
+        &self.data.as_ref()[..self.image_len()]
+    }
+
+    pub fn ancillary_data(&self) -> &[u8] {
+        let data = self.data.as_ref();
+        let start = self.image_len();
+        let end = (start + self.ancillary_size).min(data.len());
+
+        &data[start..end]
+    }
+
+    // Each sample of the image in turn, unpacked or widened to u16 as it's read.
+    // Colour formats give the channels of each pixel one after another. None if the
+    // pixel format is unknown.
+    pub fn pixels(&self) -> Option<impl Iterator<Item = u16> + '_> {
+        Some(self.pixel_format?.iter_u16(self.image_data()))
+    }
+
+    pub fn unpack_data_to_u16(&self) -> Option<Vec<u16>> {
+        self.pixel_format?.unpack_to_u16(self.image_data())
+    }
+
+    // For formats with each sample in its own 16 bits, e.g. Mono12 but not Mono12p.
+    // The samples are little endian, so on little endian machines the data can
+    // be used as it is, provided it's aligned, and otherwise it's copied.
+    pub fn as_u16_slice(&self) -> Option<Cow<'_, [u16]>> {
+        let format = self.pixel_format?;
+        let len = self.width*self.height*format.num_channels();
+
+        if format.is_packed() || format.bits_per_pixel() != 16*format.num_channels() {
+            return None
+        }
+
+        let data = self.image_data().get(..2*len)?;
+
+        // Reinterpreting bytes as u16 is fine whatever the bytes are
+        let (prefix, samples, _) = unsafe { data.align_to::<u16>() };
+
+        if cfg!(target_endian = "little") && prefix.is_empty() {
+            Some(Cow::Borrowed(&samples[..len]))
+        }
+        else {
+            Some(Cow::Owned(
+                data.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect()
+            ))
+        }
+    }
+
+    // Converts the image from one format to another, keeping everything else about
+    // the frame. Anything but the deeper RGB(A) formats can become Rgb8 or Bgr8, and
+    // any Mono format can become Mono8. Other conversions give None.
+    pub fn convert(&self, from: PixelFormat, to: PixelFormat) -> Option<Frame<Vec<u8>>> {
+        use PixelFormat::*;
+
+        let data = self.image_data();
+        let pixels = self.width*self.height;
+        let size = from.image_buffer_size(self.width, self.height);
+        let is_mono = from.num_channels() == 1 && !from.is_bayer();
+
+        let converted = match to {
+            _ if from == to => data.get(..size)?.to_vec(),
+            Mono8 if is_mono => from.unpack_to_u8(data)?.get(..pixels)?.to_vec(),
+            Rgb8 => self.convert_to_rgb8(from)?,
+            Bgr8 => {
+                let mut bgr = self.convert_to_rgb8(from)?;
+
+                bgr.chunks_exact_mut(3).for_each(|p| p.swap(0, 2));
+                bgr
+            },
+            _ => return None
+        };
+
+        let mut frame = self.map_data(|_| converted);
+
+        frame.pixel_format = Some(to);
+        frame.image_size = frame.data.len();
+        frame.ancillary_size = 0;
+
+        Some(frame)
+    }
+
+    fn convert_to_rgb8(&self, from: PixelFormat) -> Option<Vec<u8>> {
+        use PixelFormat::*;
+
+        let data = self.image_data();
+        let pixels = self.width*self.height;
+
+        match from {
+            Rgb8 => data.get(..pixels*3).map(<[u8]>::to_vec),
+            Bgr8 | Rgba8 | Bgra8 => {
+                let channels = from.num_channels();
+                let (r, b) = if from == Rgba8 { (0, 2) } else { (2, 0) };
+
+                Some(data.get(..pixels*channels)?
+                         .chunks_exact(channels)
+                         .flat_map(|p| [p[r], p[1], p[b]])
+                         .collect())
+            },
+
+            Yuv411 | Yuv422 | Yuv444
+            | YCbCr411_8_CbYYCrYY | YCbCr422_8_CbYCrY | YCbCr8_CbYCr
+            => from.yuv_to_rgb8(data, self.width, self.height),
+
+            // Bayer data is brought down to 8 bits before demosaicing it
+            _ if from.is_bayer() => {
+                let bayer8 = from.bayer_8bit_equivalent()?;
+                let mosaic = from.unpack_to_u8(data)?;
+
+                bayer8.debayer_to_rgb8(&mosaic, self.width, self.height)
+            },
+
+            // Grey is just equal amounts of each colour
+            _ if from.num_channels() == 1 => {
+                let grey = from.unpack_to_u8(data)?;
+
+                Some(grey.get(..pixels)?.iter().flat_map(|&v| [v, v, v]).collect())
+            },
+
+            _ => None
+        }
+    }
+
+    #[cfg(feature = "ndarray")]
+    pub fn as_array2(&self) -> Option<ndarray::ArrayView2<'_, u8>> {
+        let format = self.pixel_format?;
+
+        if format.bits_per_pixel() != 8 { return None }
+
+        self.as_array_view((self.height, self.width), (self.width, 1))
+    }
+
+    #[cfg(feature = "ndarray")]
+    pub fn as_array3(&self) -> Option<ndarray::ArrayView3<'_, u8>> {
+        let format = self.pixel_format?;
+        let channels = format.num_channels();
+
+        // Only formats storing every channel of every pixel in its own byte can be
+        // viewed directly, which rules out packed and chroma subsampled formats.
+        if format.bits_per_channel() != 8 || format.bits_per_pixel() != channels*8 {
+            return None
+        }
+
+        let shape = (self.height, self.width, channels);
+        let strides = (self.width*channels, channels, 1);
+
+        self.as_array_view(shape, strides)
+    }
+
+    #[cfg(feature = "ndarray")]
+    fn as_array_view<D, Sh>(&self, shape: Sh, strides: Sh)
+    -> Option<ndarray::ArrayView<'_, u8, D>>
+    where D: ndarray::Dimension, Sh: ndarray::IntoDimension<Dim = D> {
+        use ndarray::ShapeBuilder;
+
+        let shape = shape.into_dimension();
+        let len = shape.size();
+
+        // The buffer may hold more than just the image (e.g. chunk data after it)
+        // but it certainly can't hold less.
+        let data = self.data.as_ref().get(..len)?;
+
+        ndarray::ArrayView::from_shape(shape.strides(strides.into_dimension()), data).ok()
+    }
+
+    #[cfg(feature = "image")]
+    pub fn to_dynamic_image(&self) -> Option<image::DynamicImage> {
+        use image::{DynamicImage, ImageBuffer};
+        use PixelFormat::*;
+
+        let format = self.pixel_format?;
+        let (width, height) = (self.width as u32, self.height as u32);
+        let pixels = self.width*self.height;
+        let data = self.data.as_ref();
+
+        // Vimba's buffers can be longer than the image itself, so only take as many
+        // components as there are in the image. Anything deeper than 8 bits is
+        // scaled up to fill the whole 16 bit range.
+        let to_u8 = |channels: usize| data.get(..pixels*channels).map(<[u8]>::to_vec);
+        let to_u16 = |channels: usize| {
+            let shift = 16 - format.bits_per_channel();
+            let mut v = format.unpack_to_u16(data)?;
+
+            v.truncate(pixels*channels);
+            v.iter_mut().for_each(|x| *x <<= shift);
+            
+            Some(v)
+        };
+
+        // Swaps the red and blue channels of BGR(A) data in place
+        fn swap_rb<C: Copy>(mut v: Vec<C>, channels: usize) -> Vec<C> {
+            v.chunks_exact_mut(channels).for_each(|p| p.swap(0, 2));
+            v
+        }
+
+        match format {
+            Mono8 => ImageBuffer::from_raw(width, height, to_u8(1)?)
+                         .map(DynamicImage::ImageLuma8),
+            Mono10 | Mono10p | Mono12 | Mono12Packed | Mono12p | Mono14 | Mono16
+            => ImageBuffer::from_raw(width, height, to_u16(1)?)
+                   .map(DynamicImage::ImageLuma16),
+
+            Rgb8 => ImageBuffer::from_raw(width, height, to_u8(3)?)
+                        .map(DynamicImage::ImageRgb8),
+            Bgr8 => ImageBuffer::from_raw(width, height, swap_rb(to_u8(3)?, 3))
+                        .map(DynamicImage::ImageRgb8),
+            Rgba8 => ImageBuffer::from_raw(width, height, to_u8(4)?)
+                         .map(DynamicImage::ImageRgba8),
+            Bgra8 => ImageBuffer::from_raw(width, height, swap_rb(to_u8(4)?, 4))
+                         .map(DynamicImage::ImageRgba8),
+
+            Rgb10 | Rgb12 | Rgb14 | Rgb16
+            => ImageBuffer::from_raw(width, height, to_u16(3)?)
+                   .map(DynamicImage::ImageRgb16),
+            Bgr10 | Bgr12 | Bgr14 | Bgr16
+            => ImageBuffer::from_raw(width, height, swap_rb(to_u16(3)?, 3))
+                   .map(DynamicImage::ImageRgb16),
+            Rgba10 | Rgba12 | Rgba14 | Rgba16
+            => ImageBuffer::from_raw(width, height, to_u16(4)?)
+                   .map(DynamicImage::ImageRgba16),
+            Bgra10 | Bgra12 | Bgra14 | Bgra16
+            => ImageBuffer::from_raw(width, height, swap_rb(to_u16(4)?, 4))
+                   .map(DynamicImage::ImageRgba16),
+
+            Yuv411 | Yuv422 | Yuv444
+            | YCbCr411_8_CbYYCrYY | YCbCr422_8_CbYCrY | YCbCr8_CbYCr => {
+                let rgb = format.yuv_to_rgb8(data, self.width, self.height)?;
+                
+                ImageBuffer::from_raw(width, height, rgb).map(DynamicImage::ImageRgb8)
+            },
+
+            // Bayer data is brought down to 8 bits before demosaicing it
+            _ => {
+                let bayer8 = format.bayer_8bit_equivalent()?;
+                let mosaic = format.unpack_to_u8(data)?;
+                let rgb = bayer8.debayer_to_rgb8(&mosaic, self.width, self.height)?;
+                
+                ImageBuffer::from_raw(width, height, rgb).map(DynamicImage::ImageRgb8)
+            }
+        }
+    }
+}
+
+impl Frame<&[u8]> {
+    unsafe fn from_c_struct_ref_data(frame: &VmbFrame_t) -> Self {
+        let buffer_ptr = frame.buffer as *const u8;
+        let buffer_size = frame.bufferSize as usize;
+        let data = std::slice::from_raw_parts(buffer_ptr, buffer_size);
+
+        Self::from_c_struct(frame, data)
+    }
+}
+
+impl<T: AsRef<[u8]>> AsRef<[u8]> for Frame<T> {
+    fn as_ref(&self) -> &[u8] {
+        self.data.as_ref()
+    }
+}
+
+impl<T: AsRef<[u8]>> Deref for Frame<T> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.data.as_ref()
+    }
+}
+
+impl<T> Default for Frame<T> where T: Default + AsRef<[u8]> {
+    fn default() -> Self {
+        Self {
+            data: T::default(),
+            width: 0,
+            height: 0,
+            offset_x: 0,
+            offset_y: 0,
+            id: 0,
+            timestamp: 0,
+            pixel_format: Some(PixelFormat::default()),
+            ancillary_size: 0,
+            image_size: 0
+        }
+    }
+}
+
+
+
+// A buffer which goes back to the pool it came from once dropped, so that after the
+// first few frames, streaming into pooled buffers doesn't need to allocate at all.
+pub struct PooledBuffer {
+    data: Vec<u8>,
+    pool: Arc<Mutex<Vec<Vec<u8>>>>
+}
+
+impl PooledBuffer {
+    fn from_pool(pool: &Arc<Mutex<Vec<Vec<u8>>>>, src: &[u8]) -> Self {
+        let mut data = pool.lock().ok().and_then(|mut free| free.pop()).unwrap_or_default();
+
+        // Buffers are all the same size while streaming, so this doesn't allocate
+        data.clear();
+        data.extend_from_slice(src);
+
+        Self { data, pool: pool.clone() }
+    }
+}
+
+impl AsRef<[u8]> for PooledBuffer {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        if let Ok(mut free) = self.pool.lock() {
+            free.push(mem::take(&mut self.data));
+        }
+    }
+}
+
+
+
+pub trait CameraCallback: Send + FnMut(Frame<&[u8]>) -> StreamContinue {}
+
+impl<T> CameraCallback for T where T: Send + FnMut(Frame<&[u8]>) -> StreamContinue {}
+
+pub type FrameBuffer = Box<dyn AsMut<[u8]> + Send>;
+
+/// Where streaming gets its frame buffers from. Some platforms only manage zero-copy
+/// DMA into buffers that are aligned or allocated in a particular way.
+///
+/// # Safety
+/// as_mut is called once on each buffer, and Vimba then writes into the slice it
+/// gave for as long as the buffer is alive, with no borrow to stop anything else
+/// touching it. So the slice must be at least the requested size, and its memory
+/// must stay valid and unused by anything else until the buffer is dropped.
+pub unsafe trait BufferProvider {
+    fn allocate(&mut self, size: usize) -> Result<FrameBuffer>;
+}
+
+// Plain zeroed Vecs, as used by start_streaming
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VecBuffers;
+
+// A boxed Vec's heap memory doesn't move or get touched without going through it
+unsafe impl BufferProvider for VecBuffers {
+    fn allocate(&mut self, size: usize) -> Result<FrameBuffer> {
+        Ok(Box::new(vec![0u8; size]))
+    }
+}
+
+// Zeroed buffers aligned to the given power of two, e.g. 4096 for page alignment
+#[derive(Clone, Copy, Debug)]
+pub struct AlignedBuffers {
+    pub alignment: usize
+}
+
+// Same as VecBuffers, with the memory owned by an AlignedBuffer instead
+unsafe impl BufferProvider for AlignedBuffers {
+    fn allocate(&mut self, size: usize) -> Result<FrameBuffer> {
+        let layout = Layout::from_size_align(size.max(1), self.alignment)
+                         .map_err(|_| Error::BadParameter)?;
+        let ptr = unsafe { alloc::alloc_zeroed(layout) };
+
+        if ptr.is_null() { return Err(Error::Resources) }
+
+        Ok(Box::new(AlignedBuffer { ptr, size, layout }))
+    }
+}
+
+struct AlignedBuffer {
+    ptr: *mut u8,
+    size: usize,
+    layout: Layout
+}
+
+// The buffer owns its memory outright, just like a Vec would
+unsafe impl Send for AlignedBuffer {}
+
+impl AsMut<[u8]> for AlignedBuffer {
+    fn as_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.size) }
+    }
+}
+
+impl Drop for AlignedBuffer {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.ptr, self.layout); }
+    }
+}
+
+pub trait RawCameraCallback: Send + FnMut(&VmbFrame_t) -> StreamContinue {}
+
+impl<T> RawCameraCallback for T where T: Send + FnMut(&VmbFrame_t) -> StreamContinue {}
+
+struct CameraCallbackContext {
+    handler: Box<dyn RawCameraCallback>,
+    frames: Vec<VmbFrame_t>,
+    buffers: Vec<FrameBuffer>,
+    stop_tx: mpsc::Sender<()>,
+    stop_rx: mpsc::Receiver<()>,
+    state: StreamState,
+    counters: Arc<StreamCounters>,
+    // What's needed to start capturing again on a new handle after reconnecting
+    callback: VmbFrameCallback,
+    acquisition_mode: AcquisitionMode,
+    depth: usize
+}
+
+// Only touched by the streaming thread until streaming has been stopped
+#[derive(Default)]
+struct StreamState {
+    stopped: bool,
+    panic: Option<Box<dyn Any + Send>>,
+    // Announced frames that aren't queued, in the order they'll next be queued
+    idle: VecDeque<*const VmbFrame_t>
+}
+
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StreamStats {
+    pub total: u64,
+    pub incomplete: u64,
+    pub delivered: u64,
+    pub discarded: u64,
+    // Delivered to the handler but then thrown away, by start_streaming_bounded
+    pub dropped: u64
+}
+
+// Frames per second over the last few frames, as seen by the host when they arrive
+// and by the camera according to the frame timestamps
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MeasuredFps {
+    pub host: f64,
+    pub device: Option<f64>
+}
+
+// Updated from the streaming thread, hence the atomics
+#[derive(Default)]
+struct StreamCounters {
+    total: AtomicU64,
+    incomplete: AtomicU64,
+    delivered: AtomicU64,
+    discarded: AtomicU64,
+    dropped: AtomicU64,
+
+    // When the most recent frames arrived, along with their timestamps
+    arrivals: Mutex<VecDeque<(Instant, u64)>>
+}
+
+impl StreamCounters {
+    fn record_arrival(&self, timestamp: u64) {
+        if let Ok(mut arrivals) = self.arrivals.lock() {
+            if arrivals.len() == FPS_WINDOW { arrivals.pop_front(); }
+
+            arrivals.push_back((Instant::now(), timestamp));
+        }
+    }
+
+    fn measured_fps(&self, timestamp_freq: Option<u64>) -> Option<MeasuredFps> {
+        let arrivals = self.arrivals.lock().ok()?;
+        let (&(first_time, first_ts), &(last_time, last_ts)) =
+            (arrivals.front()?, arrivals.back()?);
+        let intervals = (arrivals.len() - 1) as f64;
+
+        let host_secs = last_time.duration_since(first_time).as_secs_f64();
+        let device_secs = timestamp_freq.filter(|&f| f > 0)
+            .map(|f| last_ts.wrapping_sub(first_ts) as f64/f as f64);
+
+        if host_secs <= 0.0 { return None }
+
+        Some(MeasuredFps {
+            host: intervals/host_secs,
+            device: device_secs.filter(|&s| s > 0.0).map(|s| intervals/s)
+        })
+    }
+
+    fn snapshot(&self) -> StreamStats {
+        StreamStats {
+            total: self.total.load(Ordering::Relaxed),
+            incomplete: self.incomplete.load(Ordering::Relaxed),
+            delivered: self.delivered.load(Ordering::Relaxed),
+            discarded: self.discarded.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed)
         }
     }
+}
+
+
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct StreamContinue(pub bool);
+
+
+
+// Lets any thread stop frames going to the handler, e.g. from a Ctrl-C handler,
+// without needing the camera. The camera's owner still has to call stop_streaming
+// afterwards to stop acquisition and get the buffers back from Vimba.
+#[derive(Clone)]
+pub struct StreamHandle {
+    stop_tx: mpsc::Sender<()>
+}
+
+impl StreamHandle {
+    // Takes effect when the next frame arrives. Does nothing if streaming has
+    // already stopped.
+    pub fn stop(&self) {
+        let _ = self.stop_tx.send(());
+    }
+}
+
+
+
+// Turns a handler of Frames into one of Vimba's frame structs
+fn raw_handler<F: CameraCallback>(mut handler: F) -> impl RawCameraCallback {
+    move |frame: &VmbFrame_t| handler(unsafe { Frame::from_c_struct_ref_data(frame) })
+}
+
+
+
+// The receiving end of start_streaming_bounded, which always holds the most recent
+// frames. None is received once streaming has stopped and the queue is empty.
+pub struct BoundedReceiver {
+    queue: Arc<BoundedQueue>
+}
+
+struct BoundedQueue {
+    state: Mutex<BoundedState>,
+    ready: Condvar
+}
+
+struct BoundedState {
+    frames: VecDeque<Frame<Vec<u8>>>,
+    capacity: usize,
+    // Set when either end goes away
+    closed: bool
+}
+
+impl BoundedReceiver {
+    pub fn recv(&self) -> Option<Frame<Vec<u8>>> {
+        self.recv_timeout(Duration::MAX).ok()
+    }
+
+    // Gives Timeout if nothing arrived in time, or Other once streaming has stopped
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Frame<Vec<u8>>> {
+        let deadline = Instant::now().checked_add(timeout);
+        let mut state = self.queue.state.lock().map_err(|_| Error::Other)?;
+
+        loop {
+            if let Some(frame) = state.frames.pop_front() { return Ok(frame) }
+            if state.closed { return Err(Error::Other) }
+
+            state = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+
+                    if remaining.is_zero() { return Err(Error::Timeout) }
+
+                    self.queue.ready.wait_timeout(state, remaining)
+                        .map_err(|_| Error::Other)?.0
+                },
+                None => self.queue.ready.wait(state).map_err(|_| Error::Other)?
+            };
+        }
+    }
+
+    pub fn try_recv(&self) -> Option<Frame<Vec<u8>>> {
+        self.queue.state.lock().ok()?.frames.pop_front()
+    }
+}
+
+impl Iterator for BoundedReceiver {
+    type Item = Frame<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
+
+impl Drop for BoundedReceiver {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.queue.state.lock() { state.closed = true; }
+    }
+}
+
+// Lives in the streaming handler, so it's dropped when streaming stops. The lock
+// is only ever held briefly by either end, so the Vimba callback never waits long.
+struct BoundedSender {
+    queue: Arc<BoundedQueue>,
+    counters: Arc<StreamCounters>
+}
+
+impl BoundedSender {
+    // Gives false once the receiver is gone
+    fn send(&self, frame: Frame<Vec<u8>>) -> bool {
+        let Ok(mut state) = self.queue.state.lock() else { return false };
+
+        if state.closed { return false }
+
+        if state.frames.len() == state.capacity {
+            state.frames.pop_front();
+            self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+
+        state.frames.push_back(frame);
+        drop(state);
+        self.queue.ready.notify_one();
+
+        true
+    }
+}
+
+impl Drop for BoundedSender {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.queue.state.lock() { state.closed = true; }
+
+        self.queue.ready.notify_all();
+    }
+}
+
+
+
+pub struct FrameIter<'a> {
+    camera: &'a mut Camera,
+    rx: mpsc::Receiver<Frame<Vec<u8>>>
+}
+
+impl Iterator for FrameIter<'_> {
+    type Item = Frame<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.rx.recv_timeout(DISCONNECT_POLL_INTERVAL) {
+                Ok(frame) => return Some(frame),
+                Err(mpsc::RecvTimeoutError::Disconnected) => return None,
+
+                // No frames doesn't necessarily mean anything is wrong, e.g. when
+                // waiting on a trigger, but if the camera has gone away or stopped
+                // acquiring then no more frames will ever arrive.
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if self.camera.has_acquisition_status &&
+                       self.camera.get_feature_bool("AcquisitionStatus") != Ok(true) {
+                        return None
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for FrameIter<'_> {
+    fn drop(&mut self) {
+        // The camera may already be gone, so there's nothing useful to do on error
+        let _ = self.camera.stop_streaming();
+    }
+}
+
+impl<'a> FrameIter<'a> {
+    pub fn map_decoded(self, format: PixelFormat) -> DecodedFrames<'a> {
+        DecodedFrames { frames: self, format }
+    }
+}
+
+// Converts each frame as it's taken from the iterator, so the work is done on the
+// consuming thread rather than the Vimba one. Frames that can't be converted to
+// the format, including ones of an unknown format, are skipped.
+pub struct DecodedFrames<'a> {
+    frames: FrameIter<'a>,
+    format: PixelFormat
+}
+
+impl Iterator for DecodedFrames<'_> {
+    type Item = Frame<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let format = self.format;
+
+        self.frames.by_ref().find_map(|frame| frame.convert(frame.pixel_format?, format))
+    }
+}
+
+
+
+// The async equivalent of FrameIter. Frames go through an unbounded channel so that
+// the Vimba callback never has to wait on the async side.
+#[cfg(feature = "tokio")]
+pub struct FrameStream<'a> {
+    camera: &'a mut Camera,
+    rx: tokio::sync::mpsc::UnboundedReceiver<Frame<Vec<u8>>>
+}
+
+#[cfg(feature = "tokio")]
+impl futures_core::Stream for FrameStream<'_> {
+    type Item = Frame<Vec<u8>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>)
+    -> std::task::Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Drop for FrameStream<'_> {
+    fn drop(&mut self) {
+        // This blocks briefly while acquisition stops, which can't be helped here
+        let _ = self.camera.stop_streaming();
+    }
+}
+
+
+
+pub struct FeatureWatcher {
+    vimba_ctx: Arc<VimbaContext>,
+    handle: VmbHandle_t,
+    name: CString,
+    callback: VmbInvalidationCallback,
+    state: Box<WatcherState>
+}
+
+struct WatcherState {
+    handler: Box<dyn FnMut() + Send>,
+    panicked: AtomicBool,
+    // Vimba's thread sets this while the owner may be taking it
+    panic: Mutex<Option<Box<dyn Any + Send>>>
+}
+
+impl FeatureWatcher {
+    pub(crate) fn new<F>(
+        vimba_ctx: Arc<VimbaContext>, handle: VmbHandle_t, name: &str, handler: F
+    ) -> Result<Self>
+    where F: FnMut() + Send + 'static {
+        let name = CString::new(name).expect("name cannot have internal zeros");
+
+        // The state lives on the heap, so the pointer given to Vimba stays valid
+        // even when the box itself is moved into the watcher.
+        let mut state = Box::new(WatcherState {
+            handler: Box::new(handler),
+            panicked: AtomicBool::new(false),
+            panic: Mutex::new(None)
+        });
+        let state_ptr = state.as_mut() as *mut WatcherState as *mut std::ffi::c_void;
+
+        unsafe extern "C" fn wrapper(
+            _: VmbHandle_t, _: *const i8, ctx: *mut std::ffi::c_void
+        ) {
+            let state = &mut *(ctx as *mut WatcherState);
+            let handler = &mut state.handler;
+
+            // Unwinding out of an extern "C" function is undefined behaviour, so a
+            // handler that panics is caught, and never run again. The payload is kept
+            // for take_panic, as its owner may not be around to hear about it.
+            if state.panicked.load(Ordering::Relaxed) { return }
+
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(handler)) {
+                #[cfg(feature = "log")]
+                log::error!(target: "vimba", "Feature watcher handler panicked");
+
+                state.panicked.store(true, Ordering::Relaxed);
+                let mut slot = state.panic.lock().unwrap_or_else(PoisonError::into_inner);
+
+                *slot = Some(payload);
+            }
+        }
+
+        let callback: VmbInvalidationCallback = Some(wrapper);
+
+        vmbcall!(
+            VmbFeatureInvalidationRegister,
+            handle, name.as_ptr(), callback, state_ptr
+        )?;
+
+        Ok(Self { vimba_ctx, handle, name, callback, state })
+    }
+
+    // The payload of the handler's panic, if it panicked, after which it's no longer
+    // run. Resume it with std::panic::resume_unwind to pass the panic on.
+    pub fn take_panic(&self) -> Option<Box<dyn Any + Send>> {
+        self.state.panic.lock().unwrap_or_else(PoisonError::into_inner).take()
+    }
+}
+
+impl Drop for FeatureWatcher {
+    fn drop(&mut self) {
+        // If the camera has already been closed then this fails, but in that case
+        // Vimba has forgotten about the callback anyway, so it's safe to ignore.
+        unsafe {
+            VmbFeatureInvalidationUnregister(self.handle, self.name.as_ptr(), self.callback);
+        }
+    }
+}
+
+
+
+// How Camera::enable_auto_reconnect goes about reopening a camera that's gone away
+pub struct ReconnectPolicy {
+    pub max_attempts: u32,
+    // Waited after the first failed attempt, and doubled after each one after that
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    give_up: Option<GiveUpHandler>
+}
+
+type GiveUpHandler = Box<dyn FnMut(&Error) + Send>;
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            give_up: None
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    // Called with the last error once every attempt has failed
+    pub fn on_give_up<F>(mut self, handler: F) -> Self
+    where F: FnMut(&Error) + Send + 'static {
+        self.give_up = Some(Box::new(handler));
+        self
+    }
+}
+
+struct Reconnect {
+    lost: Arc<AtomicBool>,
+    watcher: Option<FeatureWatcher>,
+    worker: Option<thread::JoinHandle<()>>
+}
+
+impl Drop for Reconnect {
+    fn drop(&mut self) {
+        // Dropping the watcher drops the sender the worker waits on, which ends it.
+        // If the give-up handler panicked, the worker has already ended anyway.
+        self.watcher = None;
+
+        if let Some(worker) = self.worker.take() { let _ = worker.join(); }
+    }
+}
+
+// Owns the retry loop, so a lost camera is reopened without its owner having to do
+// anything. It runs until reconnecting is disabled or the camera is closed.
+struct ReconnectWorker {
+    link: Arc<Mutex<Link>>,
+    id: CString,
+    access_mode: AccessMode,
+    settings: HashMap<String, OwnedFeatureValue>,
+    policy: ReconnectPolicy,
+    lost: Arc<AtomicBool>,
+    // Sent a () each time the camera is lost
+    events: mpsc::Receiver<()>,
+    // Vimba has to stay started for as long as the worker might use it
+    _vimba_ctx: Arc<VimbaContext>
+}
+
+impl ReconnectWorker {
+    fn run(mut self) {
+        while self.events.recv().is_ok() {
+            self.lost.store(true, Ordering::Relaxed);
+
+            let mut backoff = self.policy.backoff;
+            let mut res = Err(Error::NotFound);
+
+            for attempt in 0..self.policy.max_attempts {
+                if attempt > 0 {
+                    if !self.wait(backoff) { return }
+
+                    backoff = (backoff*2).min(self.policy.max_backoff);
+                }
+
+                res = self.attempt();
+
+                if res.is_ok() { break }
+            }
+
+            match res {
+                Ok(()) => self.lost.store(false, Ordering::Relaxed),
+                Err(e) => if let Some(give_up) = self.policy.give_up.as_mut() {
+                    give_up(&e)
+                }
+            }
+        }
+    }
+
+    // Sleeps between attempts, giving false as soon as reconnecting is disabled
+    fn wait(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now().checked_add(timeout);
+
+        loop {
+            match recv_by(&self.events, deadline) {
+                // Just another event about the same loss
+                Ok(()) => continue,
+                Err(Error::Timeout) => return true,
+                Err(_) => return false
+            }
+        }
+    }
+
+    // The link stays locked throughout, so the camera can't start or stop streaming
+    // in the middle of an attempt
+    fn attempt(&mut self) -> Result<()> {
+        // Anything that arrived before now is about the loss being dealt with
+        while self.events.try_recv().is_ok() {}
+
+        let mut link = lock_link(&self.link);
+
+        // Until the old handle is closed, Vimba may still call back into the stream's
+        // frames, so they can't be announced again. A failed attempt leaves its new
+        // handle here, for the next attempt to close.
+        if !link.handle.is_null() {
+            vmbcall!(VmbCameraClose, link.handle)?;
+
+            link.handle = ptr::null_mut();
+        }
+
+        let mut handle: VmbHandle_t = ptr::null_mut();
+
+        vmbcall!(VmbCameraOpen, self.id.as_ptr(), self.access_mode.bits(), &mut handle)?;
+
+        link.handle = handle;
+
+        let values = self.settings.iter()
+            .map(|(name, value)| (name.clone(), value.as_borrowed()))
+            .collect();
+
+        handle.restore(&values)?;
+
+        // A stream that its handler had already ended stays ended
+        if let Some(mut ctx) = link.stream {
+            let ctx = unsafe { ctx.as_mut() };
+
+            if !ctx.state.stopped { unsafe { start_capture(handle, ctx)?; } }
+        }
+
+        Ok(())
+    }
+}
+
+// What the camera shares with its reconnect worker. Whoever holds the lock may use
+// the handle, which is null while the camera is lost and its old handle closed.
+struct Link {
+    handle: VmbHandle_t,
+    // The camera's callback context while it's streaming. The camera owns it, and
+    // only drops it with the link locked, after taking it out of here.
+    stream: Option<NonNull<CameraCallbackContext>>
+}
+
+// Only ever used with the lock held, as Vimba requires of the handle
+unsafe impl Send for Link {}
+
+// The worker never leaves the link half updated, even if something panics
+fn lock_link(link: &Mutex<Link>) -> MutexGuard<'_, Link> {
+    link.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// Announces the context's frames and starts acquiring into them, both when streaming
+// starts and when restarting it on a new handle after reconnecting. Gives whether the
+// camera has AcquisitionStatus, for stopping to wait on.
+//
+// The context mustn't move or be dropped until its frames are revoked, which closing
+// the handle also does.
+unsafe fn start_capture(handle: VmbHandle_t, ctx: &mut CameraCallbackContext)
+-> Result<bool> {
+    for frame in &ctx.frames {
+        vmbcall!(VmbFrameAnnounce, handle, frame, FRAME_SIZE)?;
+    }
+
+    // Want the AcquisitionStatus feature to hold whether we're acquiring, if the
+    // camera has it. Simpler cameras don't, and there's no selector to set if
+    // AcquisitionStatus is the only status there is.
+    let has_acquisition_status = handle.is_readable("AcquisitionStatus") == Ok(true);
+    let has_selector = handle.is_writable("AcquisitionStatusSelector") == Ok(true);
+
+    if has_acquisition_status && has_selector {
+        handle.set_feature_enum("AcquisitionStatusSelector", "AcquisitionActive")?;
+    }
+
+    handle.set_feature_enum("AcquisitionMode", ctx.acquisition_mode.feature_value())?;
+
+    if let AcquisitionMode::MultiFrame(count) = ctx.acquisition_mode {
+        handle.set_feature_int("AcquisitionFrameCount", count as i64)?;
+    }
+
+    // Enter capture mode and queue as many frames as the queue depth allows, to be
+    // filled in order. The rest are queued as the earlier ones come back.
+    vmbcall!(VmbCaptureStart, handle)?;
+
+    let (queued, idle) = ctx.frames.split_at(ctx.depth);
+
+    for frame in queued {
+        vmbcall!(VmbCaptureFrameQueue, handle, frame, ctx.callback)?;
+    }
+
+    ctx.state.idle = idle.iter().map(|frame| frame as *const VmbFrame_t).collect();
+
+    handle.run_command("AcquisitionStart")?;
+
+    Ok(has_acquisition_status)
+}
+
+
+
+// For features only some cameras have, like the GigE ones or user sets, a missing
+// feature means the camera doesn't support what was asked of it
+fn not_found_as_unsupported(e: Error) -> Error {
+    if e == Error::NotFound { Error::NotSupported } else { e }
+}
+
+// Waits for the next frame until the deadline, if there is one. Without one, as when
+// a timeout of Duration::MAX doesn't fit in an Instant, it just waits.
+fn recv_by<T>(rx: &mpsc::Receiver<T>, deadline: Option<Instant>) -> Result<T> {
+    match deadline {
+        Some(deadline) => {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+
+            rx.recv_timeout(timeout).map_err(|e| match e {
+                mpsc::RecvTimeoutError::Timeout => Error::Timeout,
+                mpsc::RecvTimeoutError::Disconnected => Error::Other
+            })
+        },
+        None => rx.recv().map_err(|_| Error::Other)
+    }
+}
+
+// Index 0 is the factory default set, and the rest are UserSet1 and so on
+fn user_set_name(index: u32) -> String {
+    if index == 0 { "Default".to_string() } else { format!("UserSet{index}") }
+}
+
+fn path_to_cstring(path: &Path) -> Result<CString> {
+    let path = path.to_str().ok_or(Error::BadParameter)?;
+
+    CString::new(path).map_err(|_| Error::BadParameter)
+}
+
+// Settings to apply right before streaming starts, in an order that works, since
+// e.g. the allowed ROI depends on the pixel format and the allowed frame rate on
+// both. Anything not given is left as it is.
+pub struct StreamConfig<'a> {
+    camera: &'a mut Camera,
+    buffers: usize,
+    pixel_format: Option<PixelFormat>,
+    roi: Option<(i64, i64, i64, i64)>,
+    acquisition_mode: Option<AcquisitionMode>,
+    frame_rate: Option<f64>,
+    queue_depth: Option<usize>,
+    skip_frames: usize
+}
+
+impl<'a> StreamConfig<'a> {
+    fn new(camera: &'a mut Camera) -> Self {
+        Self {
+            camera,
+            buffers: DEFAULT_STREAM_BUFFERS,
+            pixel_format: None,
+            roi: None,
+            acquisition_mode: None,
+            frame_rate: None,
+            queue_depth: None,
+            skip_frames: 0
+        }
+    }
+
+    pub fn buffers(mut self, buffers: usize) -> Self {
+        self.buffers = buffers;
+        self
+    }
+
+    pub fn pixel_format(mut self, format: PixelFormat) -> Self {
+        self.pixel_format = Some(format);
+        self
+    }
+
+    pub fn roi(mut self, width: i64, height: i64, offset_x: i64, offset_y: i64) -> Self {
+        self.roi = Some((width, height, offset_x, offset_y));
+        self
+    }
+
+    pub fn acquisition_mode(mut self, mode: AcquisitionMode) -> Self {
+        self.acquisition_mode = Some(mode);
+        self
+    }
+
+    pub fn frame_rate(mut self, fps: f64) -> Self {
+        self.frame_rate = Some(fps);
+        self
+    }
+
+    pub fn queue_depth(mut self, depth: usize) -> Self {
+        self.queue_depth = Some(depth);
+        self
+    }
+
+    // Frames left over in the camera from before, e.g. after restarting quickly,
+    // can come through first. These are thrown away without reaching the handler.
+    pub fn skip_frames(mut self, count: usize) -> Self {
+        self.skip_frames = count;
+        self
+    }
+
+    pub fn start<F>(self, mut handler: F) -> Result<()>
+    where F: CameraCallback + 'static {
+        let camera = self.camera;
+        let mut to_skip = self.skip_frames;
+        let handler = move |frame: Frame<&[u8]>| {
+            if to_skip > 0 {
+                to_skip -= 1;
+                return StreamContinue(true)
+            }
+
+            handler(frame)
+        };
+
+        if let Some(format) = self.pixel_format { camera.set_pixel_format(format)?; }
+        if let Some((w, h, x, y)) = self.roi { camera.set_roi(w, h, x, y)?; }
+        if let Some(mode) = self.acquisition_mode { camera.set_acquisition_mode(mode); }
+        if let Some(fps) = self.frame_rate { camera.set_frame_rate(fps)?; }
+        if let Some(depth) = self.queue_depth { camera.set_queue_depth(Some(depth)); }
+
+        camera.start_streaming(handler, self.buffers)
+    }
+}
+
+
+
+// Which features get saved to or loaded from a settings file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PersistType {
+    All,
+    // Only features that can be restored, which is what works between cameras
+    #[default]
+    Streamable,
+    // Everything except lookup tables, which can be slow and large
+    NoLutData
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PersistSettings {
+    pub persist_type: PersistType,
+    // Features can depend on each other, so loading goes over them this many
+    // times, until they've all been set
+    pub max_iterations: u32
+}
+
+impl Default for PersistSettings {
+    fn default() -> Self {
+        Self { persist_type: PersistType::default(), max_iterations: 5 }
+    }
+}
+
+impl PersistSettings {
+    fn to_c_struct(self) -> VmbFeaturePersistSettings_t {
+        use VmbFeaturePersistType::*;
+
+        VmbFeaturePersistSettings_t {
+            persistType: match self.persist_type {
+                PersistType::All => VmbFeaturePersistAll,
+                PersistType::Streamable => VmbFeaturePersistStreamable,
+                PersistType::NoLutData => VmbFeaturePersistNoLUT
+            },
+            maxIterations: self.max_iterations,
+            loggingLevel: 0
+        }
+    }
+}
+
+
+
+pub struct Camera {
+    vimba_ctx: Arc<VimbaContext>,
+    link: Arc<Mutex<Link>>,
+    open: bool,
+    id: String,
+    access_mode: AccessMode,
+    cb_ctx: Option<Pin<Box<CameraCallbackContext>>>,
+    disconnect_watcher: Option<FeatureWatcher>,
+    reconnect: Option<Reconnect>,
+    stream_counters: Option<Arc<StreamCounters>>,
+    acquisition_mode: AcquisitionMode,
+    queue_depth: Option<usize>,
+    has_acquisition_status: bool,
+    pool_workers: Vec<thread::JoinHandle<()>>
+}
+
+impl Camera {
+    pub(crate) fn from_handle(
+        handle: VmbHandle_t, id: &str, access_mode: AccessMode, vimba_ctx: Arc<VimbaContext>
+    ) -> Self {
+        Self {
+            vimba_ctx,
+            link: Arc::new(Mutex::new(Link { handle, stream: None })),
+            open: true,
+            id: id.to_string(),
+            access_mode,
+            cb_ctx: None,
+            disconnect_watcher: None,
+            reconnect: None,
+            stream_counters: None,
+            acquisition_mode: AcquisitionMode::default(),
+            queue_depth: None,
+            has_acquisition_status: false,
+            pool_workers: Vec::new()
+        }
+    }
+
+    #[must_use = "errors when closing are otherwise silently ignored"]
+    pub fn close(&mut self) -> Result<()> {
+        if self.open {
+            // The worker mustn't reopen the camera once it's been closed
+            self.reconnect = None;
+
+            let mut link = lock_link(&self.link);
+
+            // A lost camera's old handle has already been closed
+            let res = if link.handle.is_null() { Ok(()) }
+                      else { vmbcall!(VmbCameraClose, link.handle) };
+
+            if res.is_ok() {
+                link.handle = ptr::null_mut();
+                link.stream = None;
+                self.open = false;
+                self.cb_ctx = None;
+            }
+            
+            res
+        }
+        else { Ok(()) }
+    }
+
+    // Vimba looks the info up by id, so this works even after the camera is closed
+    pub fn info(&self) -> Result<CameraInfo> {
+        let id = CString::new(self.id.as_str()).expect("id cannot have internal zeros");
+        let mut info = VmbCameraInfo_t::default();
+
+        vmbcall!(VmbCameraInfoQuery, id.as_ptr(), &mut info, CAMERA_INFO_SIZE)?;
+
+        Ok(CameraInfo::from_c_struct(info))
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn access_mode(&self) -> AccessMode {
+        self.access_mode
+    }
+
+    // Catches use after closing before it reaches Vimba, which would only give a
+    // much less obvious error about the handle
+    pub(crate) fn check_open(&self) -> Result<()> {
+        if self.open { Ok(()) } else { Err(Error::DeviceNotOpen) }
+    }
+
+    // Only full access can change features, which Vimba otherwise only says with a
+    // vague error once it's been asked to. A closed camera says so first.
+    pub(crate) fn check_write_access(&self) -> Result<()> {
+        self.check_open()?;
+
+        if self.access_mode.can_write_features() { Ok(()) }
+        else { Err(Error::InvalidAccess) }
+    }
+
+    // For calling Vimba functions this crate doesn't wrap. The handle is only valid
+    // while the camera is open, and it mustn't be closed or have frames announced
+    // or revoked behind the camera's back, as that would break its own bookkeeping.
+    // With auto reconnect enabled, it changes each time the camera is reopened.
+    pub fn raw_handle(&self) -> VmbHandle_t {
+        self.handle()
+    }
+
+    pub(crate) fn handle(&self) -> VmbHandle_t {
+        lock_link(&self.link).handle
+    }
+
+    pub fn feature(&self, name: &str) -> Result<Feature<'_>> {
+        Feature::new(self, name)
+    }
+
+    pub fn set_pixel_format(&self, format: PixelFormat) -> Result<()> {
+        self.set_feature_enum("PixelFormat", &format.to_string())
+    }
+
+    pub fn get_pixel_format(&self) -> Result<PixelFormat> {
+        self.get_feature_enum("PixelFormat")?.parse()
+    }
+
+    pub fn set_roi(&self, width: i64, height: i64, offset_x: i64, offset_y: i64)
+    -> Result<()> {
+        // The offsets limit how big the image can be, so clear them before resizing.
+        // Their own limits depend on the size, so they're only snapped afterwards.
+        self.set_feature_int("OffsetX", 0)?;
+        self.set_feature_int("OffsetY", 0)?;
+        self.set_feature_int("Width", self.snap_feature_int("Width", width)?)?;
+        self.set_feature_int("Height", self.snap_feature_int("Height", height)?)?;
+        self.set_feature_int("OffsetX", self.snap_feature_int("OffsetX", offset_x)?)?;
+        self.set_feature_int("OffsetY", self.snap_feature_int("OffsetY", offset_y)?)
+    }
+
+    pub fn get_roi(&self) -> Result<(i64, i64, i64, i64)> {
+        Ok((
+            self.get_feature_int("Width")?,
+            self.get_feature_int("Height")?,
+            self.get_feature_int("OffsetX")?,
+            self.get_feature_int("OffsetY")?
+        ))
+    }
+
+    // In degrees Celsius. Cameras with only one temperature have no selector, so
+    // the selector is only set when it exists.
+    pub fn temperature(&self, selector: &str) -> Result<f64> {
+        self.feature_flags("DeviceTemperature").map_err(not_found_as_unsupported)?;
+
+        match self.feature_flags("DeviceTemperatureSelector") {
+            Ok(_) => self.set_feature_enum("DeviceTemperatureSelector", selector)?,
+            Err(Error::NotFound) => (),
+            Err(e) => return Err(e)
+        }
+
+        self.get_feature_float("DeviceTemperature")
+    }
+
+    pub fn sensor_temperature(&self) -> Result<f64> {
+        self.temperature("Sensor")
+    }
+
+    // GigE only. Finds the biggest packet size that gets through to the host, which
+    // is then the camera's new packet size.
+    pub fn adjust_packet_size(&self) -> Result<i64> {
+        self.run_command_wait("GVSPAdjustPacketSize", PACKET_SIZE_ADJUST_TIMEOUT)
+            .map_err(not_found_as_unsupported)?;
+
+        self.get_feature_int("GVSPPacketSize")
+    }
+
+    // GigE only. The delay between packets, in ticks of the timestamp clock, which
+    // can help when the NIC can't keep up with the camera.
+    pub fn inter_packet_delay(&self) -> Result<i64> {
+        self.get_feature_int("GevSCPD").map_err(not_found_as_unsupported)
+    }
+
+    pub fn set_inter_packet_delay(&self, ticks: i64) -> Result<()> {
+        self.set_feature_int("GevSCPD", ticks).map_err(not_found_as_unsupported)
+    }
+
+    // The camera's GenICam XML, describing all its features. Only GigE cameras say
+    // where to find it in a way that's supported, so others give NotSupported.
+    #[cfg(feature = "genicam")]
+    pub fn genicam_xml(&self) -> Result<String> {
+        let url = self.read_memory(genicam::FIRST_URL_ADDRESS, genicam::URL_LENGTH)?;
+        let end = url.iter().position(|&b| b == 0).unwrap_or(url.len());
+        let url = std::str::from_utf8(&url[..end]).map_err(|_| Error::BadString)?;
+        let location = genicam::parse_local_url(url)?;
+        let data = self.read_memory(location.address, location.length)?;
+
+        genicam::decode_xml(&location.file_name, &data)
+    }
+
+    // Reads are split up, since the camera only sends so much in each packet
+    #[cfg(feature = "genicam")]
+    fn read_memory(&self, address: u64, len: usize) -> Result<Vec<u8>> {
+        let mut data = vec![0u8; len];
+
+        for (i, chunk) in data.chunks_mut(MEMORY_READ_CHUNK).enumerate() {
+            let chunk_address = address + (i*MEMORY_READ_CHUNK) as u64;
+            let mut read: u32 = 0;
+
+            vmbcall!(
+                VmbMemoryRead,
+                self.handle(), chunk_address, chunk.len() as u32,
+                chunk.as_mut_ptr() as *mut i8, &mut read
+            )?;
+
+            if read as usize != chunk.len() { return Err(Error::Incomplete) }
+        }
+
+        Ok(data)
+    }
+
+    // How big each frame buffer needs to be, which can be more than the image
+    // itself, e.g. when there's chunk data
+    pub fn payload_size(&self) -> Result<usize> {
+        let size = self.get_feature_int("PayloadSize")?;
+
+        usize::try_from(size).map_err(|_| Error::InvalidValue)
+    }
+
+    // Rounds down to the nearest value the feature's increment allows. Values out
+    // of range are left that way so that writing them gives a proper error.
+    fn snap_feature_int(&self, name: &str, v: i64) -> Result<i64> {
+        let (min, _) = self.get_feature_int_range(name)?;
+        let inc = self.get_feature_int_increment(name)?.max(1);
+
+        Ok(min + (v - min).div_euclid(inc)*inc)
+    }
+
+    // Reads a chunk feature, e.g. ChunkExposureTime, from the ancillary data of a
+    // frame. ChunkModeActive has to have been enabled when the frame was captured.
+    // The value is owned because the strings Vimba gives for enum features don't
+    // outlive the access to the ancillary data.
+    pub fn chunk<T: AsRef<[u8]>>(&self, frame: &Frame<T>, name: &str)
+    -> Result<OwnedFeatureValue> {
+        let data = frame.data.as_ref();
+
+        if frame.ancillary_size == 0 { return Err(Error::NotFound) }
+        if frame.image_size + frame.ancillary_size > data.len() {
+            return Err(Error::BadParameter)
+        }
+
+        // Vimba only reads from the buffer, so the cast to *mut is fine
+        let mut vmb_frame = VmbFrame_t {
+            buffer: data.as_ptr() as *mut std::ffi::c_void,
+            bufferSize: data.len() as u32,
+            receiveStatus: VmbFrameStatusType::VmbFrameStatusComplete,
+            imageSize: frame.image_size as u32,
+            ancillarySize: frame.ancillary_size as u32,
+            width: frame.width as u32,
+            height: frame.height as u32,
+            offsetX: frame.offset_x as u32,
+            offsetY: frame.offset_y as u32,
+            frameID: frame.id,
+            timestamp: frame.timestamp,
+            ..Default::default()
+        };
+        let mut handle: VmbHandle_t = std::ptr::null_mut();
+
+        vmbcall!(VmbAncillaryDataOpen, &mut vmb_frame, &mut handle)?;
+
+        let value = handle.get_feature(name).map(FeatureValue::into_owned);
+
+        vmbcall!(VmbAncillaryDataClose, handle)?;
+
+        value
+    }
+
+    pub fn timestamp_frequency(&self) -> Result<u64> {
+        // GigE cameras have a configurable tick rate, newer SFNC cameras just name
+        // it differently, and USB3 Vision cameras always count in nanoseconds.
+        for name in ["GevTimestampTickFrequency", "DeviceTimestampFrequency"] {
+            match self.get_feature_int(name) {
+                Ok(freq) => return Ok(freq as u64),
+                Err(Error::NotFound) => continue,
+                Err(e) => return Err(e)
+            }
+        }
+
+        Ok(1_000_000_000)
+    }
+
+    pub fn save_settings(&self, path: &Path) -> Result<()> {
+        self.save_settings_with(path, PersistSettings::default())
+    }
+
+    pub fn save_settings_with(&self, path: &Path, settings: PersistSettings) -> Result<()> {
+        self.check_open()?;
+
+        let path = path_to_cstring(path)?;
+        let mut settings = settings.to_c_struct();
+
+        vmbcall!(
+            VmbCameraSettingsSave,
+            self.handle(), path.as_ptr(), &mut settings, PERSIST_SETTINGS_SIZE
+        )
+    }
+
+    pub fn load_settings(&mut self, path: &Path) -> Result<()> {
+        self.load_settings_with(path, PersistSettings::default())
+    }
+
+    pub fn load_settings_with(&mut self, path: &Path, settings: PersistSettings)
+    -> Result<()> {
+        self.check_open()?;
+
+        // Checking first means a missing file comes back with the actual reason
+        std::fs::metadata(path)?;
+
+        let path = path_to_cstring(path)?;
+        let mut settings = settings.to_c_struct();
+
+        vmbcall!(
+            VmbCameraSettingsLoad,
+            self.handle(), path.as_ptr(), &mut settings, PERSIST_SETTINGS_SIZE
+        )
+    }
+
+    pub fn watch_feature<F>(&mut self, name: &str, handler: F) -> Result<FeatureWatcher>
+    where F: FnMut() + Send + 'static {
+        self.check_open()?;
+
+        FeatureWatcher::new(self.vimba_ctx.clone(), self.handle(), name, handler)
+    }
+
+    pub fn on_disconnect<F>(&mut self, handler: F) -> Result<()>
+    where F: FnMut() + Send + 'static {
+        // Unregister any previous handler before its replacement is registered
+        self.disconnect_watcher = None;
+        self.disconnect_watcher = Some(self.watch_disconnect(handler)?);
+
+        Ok(())
+    }
+
+    // Vimba announces cameras coming and going through the DiscoveryCameraEvent
+    // feature of the global handle, with DiscoveryCameraIdent holding the camera id
+    fn watch_disconnect<F>(&self, mut handler: F) -> Result<FeatureWatcher>
+    where F: FnMut() + Send + 'static {
+        let id = self.id.clone();
+        let wrapper = move || {
+            let ident = GLOBAL_HANDLE.get_feature_string("DiscoveryCameraIdent");
+            let event = GLOBAL_HANDLE.get_feature_enum("DiscoveryCameraEvent");
+
+            if ident.as_deref() == Ok(id.as_str())
+            && matches!(event, Ok("Missing") | Ok("Unreachable")) {
+                handler();
+            }
+        };
+
+        FeatureWatcher::new(
+            self.vimba_ctx.clone(), GLOBAL_HANDLE, "DiscoveryCameraEvent", wrapper
+        )
+    }
+
+    // Saves the current feature values, and from then on, whenever the camera is
+    // lost, has a worker thread reopen it with the same id and access mode, restore
+    // them, and restart streaming with the same handler and buffers if it was
+    // streaming. Attempts follow the policy, and its give-up handler gets the last
+    // error if none succeed. Until then, using the camera gives errors about its
+    // handle. Watchers from watch_feature don't survive, and closing the camera or
+    // enabling this again stops the previous worker.
+    pub fn enable_auto_reconnect(&mut self, policy: ReconnectPolicy) -> Result<()> {
+        self.check_open()?;
+
+        self.reconnect = None;
+
+        let settings = self.snapshot()?.into_iter()
+            .map(|(name, value)| (name, value.into_owned()))
+            .collect();
+        let lost = Arc::new(AtomicBool::new(false));
+        let (events_tx, events_rx) = mpsc::channel();
+        let watcher = self.watch_disconnect(move || { let _ = events_tx.send(()); })?;
+        let worker = ReconnectWorker {
+            link: self.link.clone(),
+            id: CString::new(self.id.as_str()).expect("id cannot have internal zeros"),
+            access_mode: self.access_mode,
+            settings,
+            policy,
+            lost: lost.clone(),
+            events: events_rx,
+            _vimba_ctx: self.vimba_ctx.clone()
+        };
+
+        self.reconnect = Some(Reconnect {
+            lost,
+            watcher: Some(watcher),
+            worker: Some(thread::spawn(move || worker.run()))
+        });
+
+        Ok(())
+    }
+
+    pub fn disable_auto_reconnect(&mut self) {
+        self.reconnect = None;
+    }
+
+    // Whether the camera has been lost and not yet reconnected
+    pub fn is_lost(&self) -> bool {
+        self.reconnect.as_ref().is_some_and(|r| r.lost.load(Ordering::Relaxed))
+    }
+
+    pub fn stream_config(&mut self) -> StreamConfig<'_> {
+        StreamConfig::new(self)
+    }
+
+    // The frame rate is only fixed once it's enabled, on cameras that need that.
+    // Older Allied Vision cameras use a different name for it.
+    fn set_frame_rate(&self, fps: f64) -> Result<()> {
+        if self.is_writable("AcquisitionFrameRateEnable") == Ok(true) {
+            self.set_feature_bool("AcquisitionFrameRateEnable", true)?;
+        }
+
+        match self.feature_flags("AcquisitionFrameRate") {
+            Ok(_) => self.set_feature_float("AcquisitionFrameRate", fps),
+            Err(Error::NotFound) =>
+                self.set_feature_float("AcquisitionFrameRateAbs", fps),
+            Err(e) => Err(e)
+        }
+    }
+
+    // Takes effect the next time streaming is started
+    pub fn set_acquisition_mode(&mut self, mode: AcquisitionMode) {
+        self.acquisition_mode = mode;
+    }
+
+    pub fn acquisition_mode(&self) -> AcquisitionMode {
+        self.acquisition_mode
+    }
+
+    // User sets are settings presets stored in the camera itself. Index 0 is the
+    // factory default set, which can be loaded but not saved over.
+    pub fn load_user_set(&self, index: u32) -> Result<()> {
+        self.select_user_set(index)?;
+        self.run_command_wait("UserSetLoad", USER_SET_TIMEOUT)
+            .map_err(not_found_as_unsupported)
+    }
+
+    pub fn save_user_set(&self, index: u32) -> Result<()> {
+        if index == 0 { return Err(Error::InvalidValue) }
+
+        self.select_user_set(index)?;
+        self.run_command_wait("UserSetSave", USER_SET_TIMEOUT)
+            .map_err(not_found_as_unsupported)
+    }
+
+    // The set loaded when the camera powers up. Older Allied Vision cameras use a
+    // different name for it.
+    pub fn set_default_user_set(&self, index: u32) -> Result<()> {
+        let name = match self.feature_flags("UserSetDefault") {
+            Ok(_) => "UserSetDefault",
+            Err(Error::NotFound) => "UserSetDefaultSelector",
+            Err(e) => return Err(e)
+        };
+
+        self.set_feature_enum(name, &user_set_name(index))
+            .map_err(not_found_as_unsupported)
+    }
 
-    pub fn with_ref_data(&self) -> Frame<&[u8]> {
-        self.map_data(AsRef::as_ref)
+    fn select_user_set(&self, index: u32) -> Result<()> {
+        self.set_feature_enum("UserSetSelector", &user_set_name(index))
+            .map_err(not_found_as_unsupported)
     }
 
-    pub fn with_vec_data(&self) -> Frame<Vec<u8>> {
-        self.map_data(|data| data.as_ref().to_vec())
+    pub fn set_exposure_auto(&self, mode: AutoMode) -> Result<()> {
+        self.set_feature_enum(self.exposure_auto_feature()?, mode.feature_value())
     }
 
-    pub fn unpack_data_to_u16(&self) -> Option<Vec<u16>> {
-        self.format.unpack_to_u16(self.data.as_ref())
+    pub fn exposure_auto(&self) -> Result<AutoMode> {
+        self.get_auto_mode(self.exposure_auto_feature()?)
     }
-}
 
-impl Frame<&[u8]> {
-    unsafe fn from_c_struct_ref_data(frame: &VmbFrame_t) -> Self {
-        let buffer_ptr = frame.buffer as *const u8;
-        let buffer_size = frame.bufferSize as usize;
-        let data = std::slice::from_raw_parts(buffer_ptr, buffer_size);
+    pub fn set_gain_auto(&self, mode: AutoMode) -> Result<()> {
+        self.set_feature_enum("GainAuto", mode.feature_value())
+    }
 
-        Self::from_c_struct(frame, data)
+    pub fn gain_auto(&self) -> Result<AutoMode> {
+        self.get_auto_mode("GainAuto")
     }
-}
 
-impl<T> Default for Frame<T> where T: Default + AsRef<[u8]> {
-    fn default() -> Self {
-        Self {
-            data: T::default(),
-            width: 0,
-            height: 0,
-            offset_x: 0,
-            offset_y: 0,
-            id: 0,
-            timestamp: 0,
-            format: PixelFormat::default()
+    // Some cameras call it ExposureAutoMode instead
+    fn exposure_auto_feature(&self) -> Result<&'static str> {
+        match self.feature_flags("ExposureAuto") {
+            Ok(_) => Ok("ExposureAuto"),
+            Err(Error::NotFound) => Ok("ExposureAutoMode"),
+            Err(e) => Err(e)
         }
     }
-}
 
+    // Cameras can have their own extra modes, which give InvalidValue
+    fn get_auto_mode(&self, name: &str) -> Result<AutoMode> {
+        AutoMode::from_feature_value(self.get_feature_enum(name)?).ok_or(Error::InvalidValue)
+    }
 
+    // How many of the announced buffers are queued with Vimba at once, with the rest
+    // waiting their turn. None queues them all. Takes effect the next time streaming
+    // is started.
+    pub fn set_queue_depth(&mut self, depth: Option<usize>) {
+        self.queue_depth = depth;
+    }
 
-pub trait CameraCallback: Send + FnMut(Frame<&[u8]>) -> StreamContinue {}
+    pub fn queue_depth(&self) -> Option<usize> {
+        self.queue_depth
+    }
 
-impl<T> CameraCallback for T where T: Send + FnMut(Frame<&[u8]>) -> StreamContinue {}
+    pub fn get_frame(&mut self) -> Result<Frame<Vec<u8>>> {
+        // recv_timeout treats a timeout this long as having no timeout at all
+        self.get_frame_timeout(Duration::MAX)
+    }
 
-struct CameraCallbackContext {
-    handler: Box<dyn CameraCallback>,
-    frames: Vec<VmbFrame_t>,
-    buffers: Vec<Vec<u8>>,
-    stop_tx: mpsc::Sender<()>,
-    stop_rx: mpsc::Receiver<()>,
-    stopped: bool
-}
+    pub fn get_frame_timeout(&mut self, timeout: Duration) -> Result<Frame<Vec<u8>>> {
+        let (tx, rx) = mpsc::channel::<Frame<_>>();
+        let handler = move |frame: Frame<&[u8]>| {
+            // Sending only fails if we've already given up waiting for the frame
+            let _ = tx.send(frame.with_vec_data());
 
+            StreamContinue(false)
+        };
+        
+        // Using 2 buffers here in case streaming doesn't stop fast enough
+        self.start_streaming(handler, 2)?;
 
+        // Streaming has to be stopped whether or not a frame arrived in time
+        let res = rx.recv_timeout(timeout);
 
-#[derive(PartialEq, Clone, Copy, Debug)]
-pub struct StreamContinue(pub bool);
+        self.stop_streaming()?;
 
+        res.map_err(|e| match e {
+            mpsc::RecvTimeoutError::Timeout => Error::Timeout,
+            mpsc::RecvTimeoutError::Disconnected => Error::Other
+        })
+    }
+    
+    // Streams until exactly count frames have arrived. If they don't all arrive
+    // within the timeout, it's an error, rather than a short burst.
+    pub fn grab(&mut self, count: usize, buffers: usize, timeout: Duration)
+    -> Result<Vec<Frame<Vec<u8>>>> {
+        if count == 0 { return Ok(Vec::new()) }
 
+        let (tx, rx) = mpsc::channel::<Frame<_>>();
+        let mut remaining = count;
+        let handler = move |frame: Frame<&[u8]>| {
+            let _ = tx.send(frame.with_vec_data());
+            remaining -= 1;
 
-pub struct Camera {
-    vimba_ctx: Arc<VimbaContext>,
-    handle: VmbHandle_t,
-    open: bool,
-    cb_ctx: Option<Pin<Box<CameraCallbackContext>>>
-}
+            StreamContinue(remaining > 0)
+        };
 
-impl Camera {
-    pub(crate) fn from_handle(handle: VmbHandle_t, vimba_ctx: Arc<VimbaContext>) -> Self {
-        Self { vimba_ctx, handle, open: true, cb_ctx: None }
-    }
+        self.start_streaming(handler, buffers)?;
 
-    pub fn close(&mut self) -> Result<()> {
-        if self.open {
-            let res = vmbcall!(VmbCameraClose, self.handle);
+        let deadline = Instant::now().checked_add(timeout);
+        let mut frames = Vec::with_capacity(count);
+        let res = (0..count).try_for_each(|_| {
+            frames.push(recv_by(&rx, deadline)?);
 
-            if res.is_ok() {
-                self.open = false;
-                self.cb_ctx = None;
-            }
-            
-            res
-        }
-        else { Ok(()) }
+            Ok(())
+        });
+
+        // Streaming has to be stopped whether or not all the frames arrived
+        self.stop_streaming()?;
+
+        res.map(|_| frames)
     }
 
-    pub fn get_frame(&mut self) -> Result<Frame<Vec<u8>>> {
+    pub fn start_streaming<F>(&mut self, handler: F, buffers: usize) -> Result<()>
+    where F: CameraCallback + 'static {
+        self.start_streaming_with_buffers(handler, buffers, &mut VecBuffers)
+    }
+
+    // Has the camera itself acquire count frames in MultiFrame mode from a single
+    // AcquisitionStart. Unlike grab, a burst that comes up short isn't an error, so
+    // whatever arrived by the timeout is returned. The acquisition mode is put back
+    // afterwards.
+    pub fn capture_burst(&mut self, count: u32, buffers: usize, timeout: Duration)
+    -> Result<Vec<Frame<Vec<u8>>>> {
+        if count == 0 { return Ok(Vec::new()) }
+
         let (tx, rx) = mpsc::channel::<Frame<_>>();
+        let mut remaining = count;
         let handler = move |frame: Frame<&[u8]>| {
-            tx.send(frame.with_vec_data()).unwrap();
+            let _ = tx.send(frame.with_vec_data());
+            remaining -= 1;
 
-            StreamContinue(false)
+            StreamContinue(remaining > 0)
         };
-        
-        // Using 2 buffers here in case streaming doesn't stop fast enough
-        self.stream(handler, 2)?;
+        let previous_mode = self.acquisition_mode;
+
+        self.acquisition_mode = AcquisitionMode::MultiFrame(count);
 
-        Ok(rx.recv().unwrap())
+        let res = self.start_streaming(handler, buffers);
+
+        self.acquisition_mode = previous_mode;
+        res?;
+
+        let deadline = Instant::now().checked_add(timeout);
+        let mut frames = Vec::with_capacity(count as usize);
+
+        while frames.len() < count as usize {
+            match recv_by(&rx, deadline) {
+                Ok(frame) => frames.push(frame),
+                Err(_) => break
+            }
+        }
+
+        self.stop_streaming()?;
+
+        Ok(frames)
     }
-    
-    // This is the most horrible thing I have ever written. God bless.
-    pub fn start_streaming<F>(&mut self, handler: F, buffers: usize) -> Result<()>
+
+    pub fn start_streaming_with_buffers<F>(
+        &mut self,
+        handler: F,
+        buffers: usize,
+        provider: &mut dyn BufferProvider
+    ) -> Result<()>
     where F: CameraCallback + 'static {
+        let handler = raw_handler(handler);
+
+        self.start_streaming_raw_with_buffers(handler, buffers, provider, Arc::default())
+    }
+
+    // Hands the handler Vimba's own frame struct, for anything Frame leaves out. It's
+    // only valid during the call, and the buffer it points to is refilled afterwards.
+    pub fn start_streaming_raw<F>(&mut self, handler: F, buffers: usize) -> Result<()>
+    where F: RawCameraCallback + 'static {
+        let counters = Arc::default();
+
+        self.start_streaming_raw_with_buffers(handler, buffers, &mut VecBuffers, counters)
+    }
+
+    // This is the most horrible thing I have ever written. God bless. The counters
+    // are passed in for handlers that count things of their own, like dropped frames.
+    fn start_streaming_raw_with_buffers<F>(
+        &mut self,
+        handler: F,
+        buffers: usize,
+        provider: &mut dyn BufferProvider,
+        counters: Arc<StreamCounters>
+    ) -> Result<()>
+    where F: RawCameraCallback + 'static {
+        self.check_open()?;
+
+        // Only full access allows acquisition, as in the table by AccessMode
+        if !self.access_mode.can_stream() { return Err(Error::InvalidAccess) }
+
         if self.cb_ctx.is_some() { return Err(Error::DeviceBusy) }
         
-        let size = self.get_feature_int("PayloadSize")?;
+        let size = self.payload_size()?;
         let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        let mut frame_buffers = Vec::with_capacity(buffers);
+        let mut buffer_ptrs = Vec::with_capacity(buffers);
+
+        // The slice is only asked for once, so the length checked is the length of
+        // the memory that Vimba will actually be given
+        for _ in 0..buffers {
+            let mut buffer = provider.allocate(size)?;
+            let slice: &mut [u8] = (*buffer).as_mut();
+
+            if slice.len() < size { return Err(Error::BadParameter) }
+
+            buffer_ptrs.push(slice.as_mut_ptr());
+            frame_buffers.push(buffer);
+        }
 
         // All this stuff mustn't move while the streaming thread is running so,
         // regrettably, it does need to be enclosed in a pin.
         let mut cb_ctx = Box::pin(CameraCallbackContext {
             handler: Box::new(handler),
             frames: vec![VmbFrame_t::default(); buffers],
-            buffers: vec![vec![0u8; size as usize]; buffers],
+            buffers: frame_buffers,
             stop_tx,
             stop_rx,
-            stopped: false
+            state: StreamState::default(),
+            counters: counters.clone(),
+            callback: Some(wrapper::<F>),
+            acquisition_mode: self.acquisition_mode,
+            depth: self.queue_depth.map_or(buffers, |depth| depth.max(1).min(buffers))
         });
 
         // Now that it's pinned, we can take pointers without worrying about them
         // becoming invalid later on. The context pointers will be read inside the
         // streaming thread to reference things on the rust side from the C side.
         let stop_rx_ptr = &mut cb_ctx.stop_rx as *mut _ as *mut std::ffi::c_void;
-        let stopped_ptr = &mut cb_ctx.stopped as *mut bool as *mut std::ffi::c_void;
-        let handler_ptr = cb_ctx.handler.as_mut() as *mut dyn CameraCallback
+        let state_ptr = &mut cb_ctx.state as *mut StreamState as *mut std::ffi::c_void;
+        let handler_ptr = cb_ctx.handler.as_mut() as *mut dyn RawCameraCallback
                                                   as *mut std::ffi::c_void;
+        let counters_ptr = Arc::as_ptr(&cb_ctx.counters) as *mut std::ffi::c_void;
 
-        for i in 0..buffers {
-            cb_ctx.frames[i].buffer = cb_ctx.buffers[i].as_mut_ptr() as *mut std::ffi::c_void;
+        for (i, buffer) in buffer_ptrs.into_iter().enumerate() {
+            cb_ctx.frames[i].buffer = buffer as *mut std::ffi::c_void;
             cb_ctx.frames[i].bufferSize = size as u32;
             cb_ctx.frames[i].context[0] = handler_ptr;
             cb_ctx.frames[i].context[1] = stop_rx_ptr;
-            cb_ctx.frames[i].context[2] = stopped_ptr;
-            
-            // Tell vimba this frame exists
-            vmbcall!(VmbFrameAnnounce, self.handle, &cb_ctx.frames[i], FRAME_SIZE)?;
+            cb_ctx.frames[i].context[2] = state_ptr;
+            cb_ctx.frames[i].context[3] = counters_ptr;
         }
         
         // This is the actual Vimba callback. It'll run the given handler until it
         // returns StreamContinue(false), or until we tell streaming to stop by sending
         // a () down the stop_tx --> stop_rx channel.
         unsafe extern "C" fn wrapper<F>(cam: VmbHandle_t, frame: *mut VmbFrame_t)
-        where F: CameraCallback {
-            let stopped = (*frame).context[2] as *mut bool;
+        where F: RawCameraCallback {
+            let state = &mut *((*frame).context[2] as *mut StreamState);
             let stop_rx = &mut *((*frame).context[1] as *mut mpsc::Receiver::<()>);
+            let counters = &*((*frame).context[3] as *const StreamCounters);
+
+            counters.total.fetch_add(1, Ordering::Relaxed);
+            counters.record_arrival((*frame).timestamp);
+
+            if (*frame).receiveStatus != VmbFrameStatusType::VmbFrameStatusComplete {
+                counters.incomplete.fetch_add(1, Ordering::Relaxed);
+            }
             
-            if stop_rx.try_recv() == Ok(()) { *stopped = true; }
-            if *stopped { return; }
+            if stop_rx.try_recv() == Ok(()) { state.stopped = true; }
+            if state.stopped {
+                counters.discarded.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+
+            counters.delivered.fetch_add(1, Ordering::Relaxed);
 
             let handler = &mut *((*frame).context[0] as *mut F);
-            let frame_rs = Frame::from_c_struct_ref_data(&*frame);
             
-            if handler(frame_rs) == StreamContinue(false) {
-                *stopped = true;
+            // Unwinding out of an extern "C" function is undefined behaviour, so a
+            // panicking handler ends the stream, and stop_streaming panics again
+            // with the same payload on the camera's own thread.
+            let action = panic::catch_unwind(AssertUnwindSafe(|| handler(&*frame)))
+                .unwrap_or_else(|payload| {
+                    state.panic = Some(payload);
+                    StreamContinue(false)
+                });
+
+            if action == StreamContinue(false) {
+                state.stopped = true;
             }
             else {
-                vmbcall!(VmbCaptureFrameQueue, cam, frame, Some(wrapper::<F>)).unwrap();
+                // Panicking here would unwind into Vimba's thread, so if the frame
+                // can't be requeued then streaming just has to stop. The raw call
+                // is used since vmbcall! panics on unknown error codes.
+                state.idle.push_back(frame);
+
+                let next = state.idle.pop_front().unwrap_or(frame);
+                let res = VmbCaptureFrameQueue(cam, next, Some(wrapper::<F>));
+
+                if res != VmbErrorType::VmbErrorSuccess { state.stopped = true; }
             }
         }
         
-        // Want the AcquisitionStatus feature to hold whether we're acquiring, and
-        // since we're intending to stream, the acquisition had better be continuous.
-        self.set_feature_enum("AcquisitionStatusSelector", "AcquisitionActive")?;
-        self.set_feature_enum("AcquisitionMode", "Continuous")?;
-        
-        // Enter capture mode and queue all the frames to be filled in order
-        vmbcall!(VmbCaptureStart, self.handle)?;
+        // The link stays locked until the context is saved, so that the reconnect
+        // worker sees either no stream at all or one that has fully started. If
+        // starting fails, the context is just deallocated.
+        let mut link = lock_link(&self.link);
 
-        for frame in &cb_ctx.frames {
-            vmbcall!(VmbCaptureFrameQueue, self.handle, frame, Some(wrapper::<F>))?;
-        }
+        self.has_acquisition_status = unsafe { start_capture(link.handle, &mut cb_ctx)? };
 
         // Save the callback context so it exists while streaming
+        link.stream = Some(NonNull::from(&mut *cb_ctx));
         self.cb_ctx = Some(cb_ctx);
-        
-        // Try to start acquiring images. If it fails, deallocate the context.
-        let res = self.run_command("AcquisitionStart");
-
-        if res.is_err() { self.cb_ctx = None; }
+        self.stream_counters = Some(counters);
 
-        res
+        Ok(())
     }
 
     pub fn stop_streaming(&mut self) -> Result<()> {
+        self.stop_streaming_with(STOP_TIMEOUT, STOP_POLL_INTERVAL)
+    }
+
+    // Waits for up to the timeout for acquisition to stop, checking whether it has
+    // every poll interval. If it doesn't stop in time, no more frames go to the
+    // handler, but the buffers stay with Vimba, so stopping can be tried again.
+    pub fn stop_streaming_with(&mut self, timeout: Duration, poll_interval: Duration)
+    -> Result<()> {
         if let Some(cb_ctx) = &self.cb_ctx {
             // Send a message telling the streaming callback to stop executing
             // the handler. Ideally this shouldn't be necessary; we should be able
             // just to run AcquisitionStop and then wait for it to stop. But doing
             // that can sometimes cut the callback off halfway through, while it's
-            // still modifying data, which is very bad.
-            cb_ctx.stop_tx.send(()).expect("Couldn't send to streaming thread");
-            
-            self.run_command("AcquisitionStop")?;
-            
-            // AcquisitionStatusMode was set to AcquisitionActive previously, so we
-            // can now check AcquisitionStatus to sleep until acquisition is done.
-            while self.get_feature_bool("AcquisitionStatus")? {
-                std::thread::sleep(std::time::Duration::from_millis(10));
+            // still modifying data, which is very bad. The receiver lives in the
+            // callback context too, so sending can't fail.
+            let _ = cb_ctx.stop_tx.send(());
+
+            // Locked throughout, so the reconnect worker can't restart the stream
+            // while it's being stopped
+            let mut link = lock_link(&self.link);
+            let handle = link.handle;
+
+            // If the camera is lost, closing its old handle already took the frames
+            // back from Vimba, so there's nothing to stop
+            if !handle.is_null() {
+                handle.run_command("AcquisitionStop")?;
+
+                // AcquisitionStatusMode was set to AcquisitionActive previously, so
+                // we can now check AcquisitionStatus to sleep until acquisition is
+                // done. Without it, all we can do is give the camera a moment.
+                if self.has_acquisition_status {
+                    let start = Instant::now();
+
+                    while handle.get_feature_bool("AcquisitionStatus")? {
+                        if start.elapsed() >= timeout { return Err(Error::Timeout) }
+
+                        std::thread::sleep(poll_interval);
+                    }
+                }
+                else {
+                    std::thread::sleep(ACQUISITION_STOP_DELAY);
+                }
+
+                // End the capture and flush out any remaining queued frames.
+                // Flushing is needed because trying to revoke a queued frame will
+                // cause Vimba to emit its very cryptic "Other" error.
+                vmbcall!(VmbCaptureEnd, handle)?;
+                vmbcall!(VmbCaptureQueueFlush, handle)?;
+
+                // Tell Vimba these frames cannot be used any more
+                for frame in &cb_ctx.frames {
+                    vmbcall!(VmbFrameRevoke, handle, frame)?;
+                }
             }
-            
-            // End the capture and flush out any remaining queued frames. Flushing
-            // is needed because trying to revoke a queued frame will cause Vimba
-            // to emit its very cryptic "Other" error.
-            vmbcall!(VmbCaptureEnd, self.handle)?;
-            vmbcall!(VmbCaptureQueueFlush, self.handle)?;
-
-            // Tell Vimba these frames cannot be used any more
-            for frame in &cb_ctx.frames {
-                vmbcall!(VmbFrameRevoke, self.handle, frame)?;
+
+            link.stream = None;
+            drop(link);
+
+            // Deallocate the callback context, passing on any panic from the handler
+            let handler_panic = self.cb_ctx.take().and_then(|mut ctx| ctx.state.panic.take());
+
+            // Dropping the handler also dropped the sender feeding any pool workers,
+            // so they'll finish off the frames they've got and then exit
+            let worker_panic = self.pool_workers.drain(..)
+                .fold(None, |first, worker| first.or(worker.join().err()));
+
+            if let Some(payload) = handler_panic.or(worker_panic) {
+                panic::resume_unwind(payload)
             }
-            
-            // Deallocate the callback context
-            self.cb_ctx = None;
         }
 
         Ok(())
     }
 
-    pub fn stream<F: CameraCallback + 'static>(&mut self, mut handler: F, buffers: usize)
+    // Blocks until the handler returns StreamContinue(false), so a handler that
+    // always continues keeps it streaming forever. Use stream_timeout to give up.
+    pub fn stream<F: CameraCallback + 'static>(&mut self, handler: F, buffers: usize)
     -> Result<()> {
+        // recv_timeout treats a timeout this long as having no timeout at all
+        self.stream_timeout(handler, buffers, Duration::MAX)
+    }
+
+    // Like stream, but stops streaming and gives Timeout if the handler hasn't
+    // ended it within the timeout
+    pub fn stream_timeout<F>(&mut self, mut handler: F, buffers: usize, timeout: Duration)
+    -> Result<()>
+    where F: CameraCallback + 'static {
         let (tx, rx) = mpsc::channel::<()>();
         let wrapper = move |frame: Frame<&[u8]>| {
-            let action = handler(frame);
+            let res = panic::catch_unwind(AssertUnwindSafe(|| handler(frame)));
 
-            if action == StreamContinue(false) { tx.send(()).unwrap(); }
+            // A panic ends streaming as well, and mustn't leave us waiting forever
+            if !matches!(res, Ok(StreamContinue(true))) { let _ = tx.send(()); }
 
-            action
+            res.unwrap_or_else(|payload| panic::resume_unwind(payload))
         };
 
         self.start_streaming(wrapper, buffers)?;
-        rx.recv().unwrap();
-        self.stop_streaming()
+
+        // The sender is only dropped along with the handler, which stays alive in
+        // the callback context until streaming stops, but don't count on it
+        let res = rx.recv_timeout(timeout).map_err(|e| match e {
+            mpsc::RecvTimeoutError::Timeout => Error::Timeout,
+            mpsc::RecvTimeoutError::Disconnected => Error::Other
+        });
+
+        // Streaming has to be stopped whether or not the handler ended it
+        self.stop_streaming()?;
+
+        res
+    }
+
+    // Streams straight out of Vimba's buffers without copying, blocking until the
+    // handler returns StreamContinue(false). The frame data is only borrowed for the
+    // duration of each call, since Vimba refills the buffer as soon as the handler
+    // returns, so anything that needs to be kept must be copied out (or use one of
+    // the queue-based methods instead). The borrow checker enforces this, because
+    // the handler has to accept a frame borrowed for any lifetime at all.
+    pub fn stream_borrowed<F>(&mut self, handler: F, buffers: usize) -> Result<()>
+    where F: CameraCallback + 'static {
+        self.stream(handler, buffers)
+    }
+
+    pub fn start_streaming_with_handle<F>(&mut self, handler: F, buffers: usize)
+    -> Result<StreamHandle>
+    where F: CameraCallback + 'static {
+        self.start_streaming(handler, buffers)?;
+
+        let stop_tx = self.cb_ctx.as_ref().ok_or(Error::Other)?.stop_tx.clone();
+
+        Ok(StreamHandle { stop_tx })
     }
 
     pub fn start_streaming_queue(
@@ -342,34 +2345,335 @@ impl Camera {
 
         self.start_streaming(handler, buffers)
     }
+
+    pub fn start_streaming_pooled(
+        &mut self, sender: mpsc::Sender<Frame<PooledBuffer>>, buffers: usize
+    ) -> Result<()> {
+        let pool = Arc::new(Mutex::new(Vec::new()));
+        let handler = move |frame: Frame<&[u8]>| {
+            let frame = frame.map_data(|data| PooledBuffer::from_pool(&pool, data));
+            let res = sender.send(frame);
+            StreamContinue(res.is_ok())
+        };
+
+        self.start_streaming(handler, buffers)
+    }
+
+    // Hands frames out to a pool of worker threads, for when processing a frame
+    // takes longer than the time between frames. Frames can finish out of order, so
+    // use their ids to put them back in order if needed. Stopping streaming waits
+    // for the workers to finish every frame they've been given.
+    pub fn start_streaming_pool<F>(&mut self, workers: usize, f: F, buffers: usize)
+    -> Result<()>
+    where F: Fn(Frame<Vec<u8>>) + Send + Sync + 'static {
+        if workers == 0 { return Err(Error::BadParameter) }
+
+        let (tx, rx) = mpsc::channel();
+        let rx = Arc::new(Mutex::new(rx));
+        let f = Arc::new(f);
+
+        self.start_streaming_queue(tx, buffers)?;
+
+        self.pool_workers = (0..workers).map(|_| {
+            let rx = rx.clone();
+            let f = f.clone();
+
+            thread::spawn(move || loop {
+                // The lock is only held while waiting for a frame, not processing it
+                let frame = match rx.lock() {
+                    Ok(rx) => rx.recv(),
+                    Err(_) => break
+                };
+
+                match frame {
+                    Ok(frame) => f(frame),
+                    Err(_) => break
+                }
+            })
+        }).collect();
+
+        Ok(())
+    }
+
+    // Frames wait in a queue of the given capacity. When the consumer falls behind
+    // and it's full, the oldest frame is dropped to make room, which counts towards
+    // the dropped frames in stream_stats. Streaming stops once the receiver is gone.
+    pub fn start_streaming_bounded(&mut self, capacity: usize, buffers: usize)
+    -> Result<BoundedReceiver> {
+        let counters = Arc::new(StreamCounters::default());
+        let queue = Arc::new(BoundedQueue {
+            state: Mutex::new(BoundedState {
+                frames: VecDeque::with_capacity(capacity),
+                capacity: capacity.max(1),
+                closed: false
+            }),
+            ready: Condvar::new()
+        });
+        let sender = BoundedSender { queue: queue.clone(), counters: counters.clone() };
+        let handler = move |frame: Frame<&[u8]>| {
+            StreamContinue(sender.send(frame.with_vec_data()))
+        };
+
+        self.start_streaming_raw_with_buffers(
+            raw_handler(handler), buffers, &mut VecBuffers, counters
+        )?;
+
+        Ok(BoundedReceiver { queue })
+    }
+
+    // Statistics for the current streaming session, or the last one if streaming
+    // has stopped. None if the camera hasn't streamed yet.
+    pub fn stream_stats(&self) -> Option<StreamStats> {
+        self.stream_counters.as_ref().map(|c| c.snapshot())
+    }
+
+    // Over the last few frames of the current streaming session, or the last one.
+    // None until at least 2 frames have arrived.
+    pub fn measured_fps(&self) -> Option<MeasuredFps> {
+        let counters = self.stream_counters.as_ref()?;
+
+        counters.measured_fps(self.timestamp_frequency().ok())
+    }
+
+    pub fn frames(&mut self, buffers: usize) -> Result<FrameIter<'_>> {
+        let (tx, rx) = mpsc::channel();
+
+        self.start_streaming_queue(tx, buffers)?;
+
+        Ok(FrameIter { camera: self, rx })
+    }
+
+    #[cfg(feature = "tokio")]
+    pub fn stream_async(&mut self, buffers: usize) -> Result<FrameStream<'_>> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let handler = move |frame: Frame<&[u8]>| {
+            let res = tx.send(frame.with_vec_data());
+            StreamContinue(res.is_ok())
+        };
+
+        self.start_streaming(handler, buffers)?;
+
+        Ok(FrameStream { camera: self, rx })
+    }
 }
 
+// Vimba handles can be used from any thread, just not from several at once. Moving
+// the camera to another thread is therefore fine, but it mustn't be Sync, which it
+// isn't thanks to the callback context. The frames in the context hold raw pointers,
+// but only to data owned by the context itself, which moves along with it.
+unsafe impl Send for Camera {}
+
+// Everything checks the camera is open first, so that using it after closing
+// gives DeviceNotOpen rather than an error about the handle
 impl HasFeatures for Camera {
+    // Vimba's enum strings only live as long as the handle, which the reconnect
+    // worker can close at any time, so they're copied out for good
     fn get_feature(&self, name: &str) -> Result<FeatureValue> {
-        self.handle.get_feature(name)
+        self.check_open()?;
+
+        match read_feature_by_name(self.handle(), name)? {
+            FeatureValue::Enum(v) => Ok(FeatureValue::Enum(intern(v))),
+            value => Ok(value)
+        }
     }
 
     fn set_feature(&self, name: &str, value: FeatureValue) -> Result<()> {
-        self.handle.set_feature(name, value)
+        self.check_write_access()?;
+        self.handle().set_feature(name, value)
     }
 
     fn list_features(&self) -> Result<Vec<FeatureInfo>> {
-        self.handle.list_features()
+        self.check_open()?;
+        self.handle().list_features()
     }
 
     fn run_command(&self, name: &str) -> Result<()> {
-        self.handle.run_command(name)
+        self.check_write_access()?;
+        self.handle().run_command(name)
     }
 
     fn is_command_done(&self, name: &str) -> Result<bool> {
-        self.handle.is_command_done(name)
+        self.check_open()?;
+        self.handle().is_command_done(name)
+    }
+
+    fn get_feature_int_range(&self, name: &str) -> Result<(i64, i64)> {
+        self.check_open()?;
+        self.handle().get_feature_int_range(name)
+    }
+
+    fn get_feature_int_increment(&self, name: &str) -> Result<i64> {
+        self.check_open()?;
+        self.handle().get_feature_int_increment(name)
+    }
+
+    fn get_feature_float_range(&self, name: &str) -> Result<(f64, f64)> {
+        self.check_open()?;
+        self.handle().get_feature_float_range(name)
+    }
+
+    fn get_feature_float_increment(&self, name: &str) -> Result<Option<f64>> {
+        self.check_open()?;
+        self.handle().get_feature_float_increment(name)
+    }
+
+    fn list_enum_entries(&self, name: &str) -> Result<Vec<String>> {
+        self.check_open()?;
+        self.handle().list_enum_entries(name)
+    }
+
+    fn is_enum_entry_available(&self, name: &str, entry: &str) -> Result<bool> {
+        self.check_open()?;
+        self.handle().is_enum_entry_available(name, entry)
+    }
+
+    fn feature_flags(&self, name: &str) -> Result<FeatureFlag> {
+        self.check_open()?;
+        self.handle().feature_flags(name)
+    }
+
+    fn get_feature_raw_length(&self, name: &str) -> Result<usize> {
+        self.check_open()?;
+        self.handle().get_feature_raw_length(name)
+    }
+
+    fn read_feature_raw_into(&self, name: &str, buf: &mut [u8]) -> Result<usize> {
+        self.check_open()?;
+        self.handle().read_feature_raw_into(name, buf)
     }
 }
 
 impl Drop for Camera {
     fn drop(&mut self) {
-        if let Err(e) = self.close() {
-            panic!("Could not close camera during drop due to error: {e:?}");
-        }
+        // Panicking here would abort if we're already unwinding, so the error is
+        // dropped. Call close() explicitly to find out if closing went wrong.
+        let _ = self.close();
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn duration(timestamp: u64, freq_hz: u64) -> Duration {
+        let frame = VmbFrame_t { timestamp, ..Default::default() };
+
+        Frame::from_c_struct(&frame, Vec::new()).timestamp_duration(freq_hz)
+    }
+
+    #[test]
+    fn timestamp_conversion() {
+        assert_eq!(duration(1_500_000_000, 1_000_000_000), Duration::from_millis(1500));
+        assert_eq!(duration(1234, 1000), Duration::from_millis(1234));
+        assert_eq!(duration(4, 3), Duration::new(1, 333_333_333));
+        assert_eq!(duration(1234, 0), Duration::ZERO);
+
+        // Ticks times a billion would overflow a u64 here, but the whole seconds are
+        // split off first
+        let max = Duration::new(18_446_744_073, 709_551_615);
+
+        assert_eq!(duration(u64::MAX, 1_000_000_000), max);
+        assert_eq!(duration(u64::MAX, 1), Duration::from_secs(u64::MAX));
+        assert_eq!(duration(u64::MAX, u64::MAX), Duration::from_secs(1));
+        assert_eq!(duration(u64::MAX - 1, u64::MAX), Duration::new(0, 999_999_999));
+    }
+
+    fn frame(data: Vec<u8>, format: u32, image_size: u32, ancillary_size: u32)
+    -> Frame<Vec<u8>> {
+        let frame = VmbFrame_t {
+            width: 2,
+            height: 2,
+            pixelFormat: format,
+            imageSize: image_size,
+            ancillarySize: ancillary_size,
+            ..Default::default()
+        };
+
+        Frame::from_c_struct(&frame, data)
+    }
+
+    #[test]
+    fn image_and_ancillary_data() {
+        let data = vec![1, 2, 3, 4, 9, 8, 7, 0];
+        let mono8 = PixelFormat::Mono8 as u32;
+        let split = |f: &Frame<Vec<u8>>| {
+            (f.image_data().to_vec(), f.ancillary_data().to_vec())
+        };
+
+        let f = frame(data.clone(), mono8, 4, 3);
+        assert_eq!(split(&f), (vec![1, 2, 3, 4], vec![9, 8, 7]));
+
+        // Without the image size from Vimba, it's worked out from the format, or
+        // failing that, whatever comes before the ancillary data
+        let f = frame(data.clone(), mono8, 0, 3);
+        assert_eq!(split(&f), (vec![1, 2, 3, 4], vec![9, 8, 7]));
+
+        let f = frame(data.clone(), 0, 0, 3);
+        assert_eq!(split(&f), (vec![1, 2, 3, 4, 9], vec![8, 7, 0]));
+
+        // Sizes bigger than the buffer are cut short rather than panicking
+        let f = frame(data.clone(), mono8, 6, 4);
+        assert_eq!(split(&f), (data[..6].to_vec(), data[6..].to_vec()));
+
+        let f = frame(data.clone(), mono8, 20, 4);
+        assert_eq!(split(&f), (data, vec![]));
+    }
+
+    #[test]
+    fn conversion() {
+        use PixelFormat::*;
+
+        let f = frame(vec![1, 2, 3, 4, 9, 9], Mono8 as u32, 4, 2);
+
+        let rgb = f.convert(Mono8, Rgb8).unwrap();
+        assert_eq!(rgb.data, [1, 1, 1, 2, 2, 2, 3, 3, 3, 4, 4, 4]);
+        assert!(rgb.pixel_format == Some(Rgb8));
+        assert_eq!(rgb.image_data(), rgb.data);
+        assert!(rgb.ancillary_data().is_empty());
+
+        let same = f.convert(Mono8, Mono8).unwrap();
+        assert_eq!(same.data, [1, 2, 3, 4]);
+
+        assert!(f.convert(Mono8, Mono12).is_none());
+
+        // Rgb8 to Bgr8 swaps the red and blue of each pixel
+        let f = frame((1..=12).collect(), Rgb8 as u32, 12, 0);
+        let bgr = f.convert(Rgb8, Bgr8).unwrap();
+        assert_eq!(bgr.data, [3, 2, 1, 6, 5, 4, 9, 8, 7, 12, 11, 10]);
+
+        // Deeper mono formats keep their top 8 bits
+        let data = vec![0xBC, 0x0A, 0x23, 0x01, 0xFF, 0x0F, 0, 0];
+        let f = frame(data, Mono12 as u32, 8, 0);
+        assert_eq!(f.convert(Mono12, Mono8).unwrap().data, [0xAB, 0x12, 0xFF, 0x00]);
+    }
+
+    #[test]
+    fn recv_without_deadline() {
+        // As in grab and capture_burst with a timeout of Duration::MAX
+        let deadline = Instant::now().checked_add(Duration::MAX);
+        let (tx, rx) = mpsc::channel();
+
+        assert_eq!(deadline, None);
+
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            tx.send(1).unwrap();
+        });
+
+        assert_eq!(recv_by(&rx, deadline), Ok(1));
+        assert_eq!(recv_by(&rx, deadline), Err(Error::Other));
+    }
+
+    #[test]
+    fn recv_past_deadline() {
+        let (tx, rx) = mpsc::channel::<i32>();
+
+        assert_eq!(recv_by(&rx, Some(Instant::now())), Err(Error::Timeout));
+
+        drop(tx);
+
+        assert_eq!(recv_by(&rx, Some(Instant::now())), Err(Error::Other));
     }
 }
@@ -1,24 +1,47 @@
 #![allow(non_upper_case_globals,dead_code)]
 
 use crate::vimba_sys::VmbErrorType;
+use crate::vimba::Version;
 use crate::Result;
-use std::fmt;
+use std::{fmt, io};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
 
 
-pub fn error_code_to_result(code: i32) -> Result<()> {
-    if code == VmbErrorType::VmbErrorSuccess { Ok(()) }
+// The function name and argument text are only used for logging, when the "log"
+// feature is enabled. Everything is logged with the target "vimba".
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+pub fn error_code_to_result(code: i32, func: &str, args: &str) -> Result<()> {
+    if code == VmbErrorType::VmbErrorSuccess {
+        #[cfg(feature = "log")]
+        log::trace!(target: "vimba", "{func}({args}) succeeded");
+
+        Ok(())
+    }
     else {
         match Error::try_from(code) {
-            Ok(e) => Err(e),
-            Err(_) => panic!("Unknown Vimba error code {code}")
+            Ok(e) => {
+                #[cfg(feature = "log")]
+                log::log!(target: "vimba", e.log_level(), "{func}({args}) failed: {e}");
+
+                Err(e)
+            },
+            Err(_) => {
+                #[cfg(feature = "log")]
+                log::error!(target: "vimba", "{func}({args}) returned unknown code {code}");
+
+                panic!("Unknown Vimba error code {code}")
+            }
         }
     }
 }
 
 
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+// Not Copy, unlike in earlier versions, since some errors carry an underlying io
+// error or the names involved. Clone is cheap for all of them.
+#[derive(Clone, Debug)]
 pub enum Error {
     // Vimba's own errors
     InternalFault,
@@ -43,7 +66,15 @@ pub enum Error {
     IO,
 
     // My additional errors
-    DeviceBusy
+    DeviceBusy,
+    UnknownFeatureType(u32),
+    BadString,
+    DuplicateSerial,
+    File(Arc<io::Error>),
+    UnknownPixelFormat,
+    SetFeatures { index: usize, name: String, error: Box<Error> },
+    UnavailableEnumEntry { name: String, value: String, available: Vec<String> },
+    VersionMismatch { found: Version, required: Version }
 }
 
 impl TryFrom<i32> for Error {
@@ -79,13 +110,140 @@ impl TryFrom<i32> for Error {
     }
 }
 
-impl std::error::Error for Error {}
+impl Error {
+    // The original Vimba error code, if this error came from Vimba itself
+    pub fn code(&self) -> Option<i32> {
+        use VmbErrorType::*;
+        use Error::*;
+
+        match self {
+            InternalFault => Some(VmbErrorInternalFault),
+            ApiNotStarted => Some(VmbErrorApiNotStarted),
+            NotFound => Some(VmbErrorNotFound),
+            BadHandle => Some(VmbErrorBadHandle),
+            DeviceNotOpen => Some(VmbErrorDeviceNotOpen),
+            InvalidAccess => Some(VmbErrorInvalidAccess),
+            BadParameter => Some(VmbErrorBadParameter),
+            StructSize => Some(VmbErrorStructSize),
+            MoreData => Some(VmbErrorMoreData),
+            WrongType => Some(VmbErrorWrongType),
+            InvalidValue => Some(VmbErrorInvalidValue),
+            Timeout => Some(VmbErrorTimeout),
+            Other => Some(VmbErrorOther),
+            Resources => Some(VmbErrorResources),
+            InvalidCall => Some(VmbErrorInvalidCall),
+            NoTL => Some(VmbErrorNoTL),
+            NotImplemented => Some(VmbErrorNotImplemented),
+            NotSupported => Some(VmbErrorNotSupported),
+            Incomplete => Some(VmbErrorIncomplete),
+            IO => Some(VmbErrorIO),
+            _ => None
+        }
+    }
+
+    // Errors that routinely come up in normal use, like probing for a feature the
+    // camera doesn't have, are only warnings.
+    #[cfg(feature = "log")]
+    fn log_level(&self) -> log::Level {
+        match self {
+            Self::NotFound | Self::Timeout | Self::InvalidAccess | Self::Incomplete |
+            Self::MoreData
+                => log::Level::Warn,
+            _ => log::Level::Error
+        }
+    }
+}
+
+// The underlying io::Error isn't comparable, so File errors are only equal if they
+// are clones of the same error
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Error::File(a), Error::File(b)) => Arc::ptr_eq(a, b),
+            (Error::UnknownFeatureType(a), Error::UnknownFeatureType(b)) => a == b,
+            (
+                Error::SetFeatures { index: i, name: n, error: e },
+                Error::SetFeatures { index: j, name: m, error: f }
+            ) => i == j && n == m && e == f,
+            (
+                Error::UnavailableEnumEntry { name: n, value: v, available: a },
+                Error::UnavailableEnumEntry { name: m, value: w, available: b }
+            ) => n == m && v == w && a == b,
+            (
+                Error::VersionMismatch { found: f, required: r },
+                Error::VersionMismatch { found: g, required: s }
+            ) => f == g && r == s,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other)
+        }
+    }
+}
+
+impl Eq for Error {}
+
+impl Hash for Error {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+
+        match self {
+            Error::File(e) => Arc::as_ptr(e).hash(state),
+            Error::UnknownFeatureType(t) => t.hash(state),
+            Error::SetFeatures { index, name, error } => {
+                index.hash(state);
+                name.hash(state);
+                error.hash(state);
+            },
+            Error::UnavailableEnumEntry { name, value, available } => {
+                name.hash(state);
+                value.hash(state);
+                available.hash(state);
+            },
+            Error::VersionMismatch { found, required } => {
+                found.hash(state);
+                required.hash(state);
+            },
+            _ => {}
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::File(e) => Some(e.as_ref()),
+            Error::SetFeatures { error, .. } => Some(error.as_ref()),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::File(Arc::new(e))
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        let kind = match &e {
+            Error::File(inner) => inner.kind(),
+            Error::NotFound => io::ErrorKind::NotFound,
+            Error::Timeout => io::ErrorKind::TimedOut,
+
+            // A transport layer failure is effectively a broken connection to the
+            // device, which is the closest thing io::ErrorKind has.
+            Error::IO => io::ErrorKind::ConnectionAborted,
+            _ => io::ErrorKind::Other
+        };
+
+        io::Error::new(kind, e)
+    }
+}
 
 impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         use Error::*;
 
-        let msg = match *self {
+        let msg = match self {
             InternalFault => "internal fault",
             ApiNotStarted => "API not started (open Vimba context needed)",
             NotFound => "device or feature not found",
@@ -101,16 +259,95 @@ impl fmt::Display for Error {
             Other => "other unknown error",
             Resources => "resources (e.g. memory) not available",
             InvalidCall => "call is invalid in the current context",
-            NoTL => "transport layer(s) not found",
+            NoTL => "transport layer(s) not found (check GENICAM_GENTL64_PATH, \
+                     GENICAM_GENTL32_PATH or VimbaConfig::transport_layer_paths)",
             NotImplemented => "not implemented",
             NotSupported => "not supported",
             Incomplete => "operation was not completed",
             IO => "transport layer I/O error",
 
             // My additional errors
-            DeviceBusy => "device busy"
+            DeviceBusy => "device busy",
+            UnknownFeatureType(_) => "feature has an unknown data type",
+            BadString => "string data was not valid UTF-8",
+            DuplicateSerial => "more than one camera has the given serial number",
+            File(_) => "file could not be accessed",
+            UnknownPixelFormat => "unknown pixel format",
+            SetFeatures { .. } => "failed to set feature",
+            UnavailableEnumEntry { .. } => "enum entry not available",
+            VersionMismatch { .. } => "installed Vimba is older than this crate supports"
+        };
+
+        match (self, self.code()) {
+            (File(e), _) => write!(fmt, "Vimba error File: {}: {}", msg, e),
+            (SetFeatures { index, name, error }, _) => write!(
+                fmt, "Vimba error SetFeatures: {} {} (index {}): {}", msg, name, index, error
+            ),
+            (UnavailableEnumEntry { name, value, available }, _) => write!(
+                fmt, "Vimba error UnavailableEnumEntry: {} {} = {} (available: {})",
+                msg, name, value, available.join(", ")
+            ),
+            (VersionMismatch { found, required }, _) => write!(
+                fmt, "Vimba error VersionMismatch: {} (found {}, need at least {})",
+                msg, found, required
+            ),
+            (_, Some(code)) => write!(fmt, "Vimba error {:?} ({}): {}", self, code, msg),
+            (_, None) => write!(fmt, "Vimba error {:?}: {}", self, msg)
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_round_trip() {
+        // Every one of Vimba's own error codes has a variant that gives it back
+        for code in VmbErrorType::VmbErrorIO..=VmbErrorType::VmbErrorInternalFault {
+            let e = Error::try_from(code).unwrap();
+
+            assert_eq!(e.code(), Some(code), "{e:?}");
+        }
+
+        assert_eq!(Error::try_from(VmbErrorType::VmbErrorSuccess), Err(()));
+        assert_eq!(Error::try_from(VmbErrorType::VmbErrorIO - 1), Err(()));
+        assert_eq!(Error::DeviceBusy.code(), None);
+
+        let timeout = error_code_to_result(VmbErrorType::VmbErrorTimeout, "", "");
+
+        assert_eq!(error_code_to_result(VmbErrorType::VmbErrorSuccess, "", ""), Ok(()));
+        assert_eq!(timeout, Err(Error::Timeout));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Error::Timeout.to_string(), "Vimba error Timeout (-12): timed out");
+        assert_eq!(Error::DeviceBusy.to_string(), "Vimba error DeviceBusy: device busy");
+
+        let e = Error::UnavailableEnumEntry {
+            name: "PixelFormat".to_string(),
+            value: "Rgb8".to_string(),
+            available: vec!["Mono8".to_string(), "Mono12".to_string()]
+        };
+
+        assert_eq!(
+            e.to_string(),
+            "Vimba error UnavailableEnumEntry: enum entry not available \
+             PixelFormat = Rgb8 (available: Mono8, Mono12)"
+        );
+
+        let e = Error::VersionMismatch {
+            found: Version { major: 1, minor: 8, patch: 4 },
+            required: Version { major: 1, minor: 9, patch: 0 }
         };
 
-        write!(fmt, "Vimba error {:?}: {}", self, msg)
+        assert_eq!(
+            e.to_string(),
+            "Vimba error VersionMismatch: installed Vimba is older than this crate \
+             supports (found 1.8.4, need at least 1.9.0)"
+        );
     }
 }
@@ -1,10 +1,14 @@
 #![allow(non_upper_case_globals,dead_code)]
 
 use crate::vimba_sys::*;
-use crate::util::pointer_to_str;
+use crate::error::Error;
+use crate::camera::Camera;
+use crate::util::{pointer_to_str, pointer_to_option_str, list_with_retry, intern};
 use crate::{vmbcall, Result};
 use std::ffi::{CStr, CString};
 use std::{ptr, mem};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use bitflags::bitflags;
 use enum_as_inner::EnumAsInner;
 
@@ -15,6 +19,7 @@ const FEATURE_INFO_SIZE: u32 = mem::size_of::<VmbFeatureInfo_t>() as u32;
 
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FeatureType {
     Int, Float, Enum, String, Bool, Command, Raw, None
 }
@@ -63,12 +68,89 @@ impl<'a> FeatureValue<'a> {
             FeatureValue::Raw(_) => FeatureType::Raw
         }
     }
+
+    pub fn into_owned(self) -> OwnedFeatureValue {
+        match self {
+            FeatureValue::Int(v) => OwnedFeatureValue::Int(v),
+            FeatureValue::Float(v) => OwnedFeatureValue::Float(v),
+            FeatureValue::Enum(v) => OwnedFeatureValue::Enum(v.to_string()),
+            FeatureValue::String(v) => OwnedFeatureValue::String(v),
+            FeatureValue::Bool(v) => OwnedFeatureValue::Bool(v),
+            FeatureValue::Raw(v) => OwnedFeatureValue::Raw(v)
+        }
+    }
+}
+
+
+
+// Like FeatureValue, but with nothing borrowed from Vimba, so it can be kept around
+// and sent between threads freely
+#[derive(Clone, Debug, PartialEq, EnumAsInner)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OwnedFeatureValue {
+    Int(i64),
+    Float(f64),
+    Enum(String),
+    String(String),
+    Bool(bool),
+    Raw(Vec<u8>)
+}
+
+impl OwnedFeatureValue {
+    pub fn feature_type(&self) -> FeatureType {
+        self.as_borrowed().feature_type()
+    }
+
+    // Raw data has to be copied, since FeatureValue owns it
+    pub fn as_borrowed(&self) -> FeatureValue<'_> {
+        match self {
+            OwnedFeatureValue::Int(v) => FeatureValue::Int(*v),
+            OwnedFeatureValue::Float(v) => FeatureValue::Float(*v),
+            OwnedFeatureValue::Enum(v) => FeatureValue::Enum(v),
+            OwnedFeatureValue::String(v) => FeatureValue::String(v.clone()),
+            OwnedFeatureValue::Bool(v) => FeatureValue::Bool(*v),
+            OwnedFeatureValue::Raw(v) => FeatureValue::Raw(v.clone())
+        }
+    }
+}
+
+impl From<FeatureValue<'_>> for OwnedFeatureValue {
+    fn from(value: FeatureValue<'_>) -> Self {
+        value.into_owned()
+    }
+}
+
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FeatureVisibility {
+    Unknown, Beginner, Expert, Guru, Invisible
+}
+
+impl TryFrom<u32> for FeatureVisibility {
+    type Error = ();
+
+    fn try_from(v: u32) -> std::result::Result<Self, Self::Error> {
+        use VmbFeatureVisibilityType::*;
+        use FeatureVisibility::*;
+
+        match v {
+            VmbFeatureVisibilityUnknown => Ok(Unknown),
+            VmbFeatureVisibilityBeginner => Ok(Beginner),
+            VmbFeatureVisibilityExpert => Ok(Expert),
+            VmbFeatureVisibilityGuru => Ok(Guru),
+            VmbFeatureVisibilityInvisible => Ok(Invisible),
+            _ => Err(())
+        }
+    }
 }
 
 
 
 bitflags! {
     #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct FeatureFlag: u32 {
         const NONE = VmbFeatureFlagsType::VmbFeatureFlagsNone;
         const READ = VmbFeatureFlagsType::VmbFeatureFlagsRead;
@@ -81,23 +163,100 @@ bitflags! {
 
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FeatureInfo {
     pub name: String,
     pub data_type: FeatureType,
-    pub flags: FeatureFlag
+    pub flags: FeatureFlag,
+    pub visibility: FeatureVisibility,
+    pub category: Option<String>,
+    pub display_name: Option<String>,
+    pub unit: Option<String>,
+    pub tooltip: Option<String>,
+    pub description: Option<String>
 }
 
 impl FeatureInfo {
-    pub fn from_c_struct(info: VmbFeatureInfo_t) -> Self {
-        Self {
+    // The type comes from the device, so one this crate doesn't know is an error
+    pub fn from_c_struct(info: VmbFeatureInfo_t) -> Result<Self> {
+        let data_type = FeatureType::try_from(info.featureDataType)
+            .map_err(|_| Error::UnknownFeatureType(info.featureDataType))?;
+
+        Ok(Self {
             name: unsafe { pointer_to_str(info.name).to_string() },
-            data_type: match FeatureType::try_from(info.featureDataType) {
-                Ok(t) => t,
-                Err(_) => panic!("Unknown Vimba feature type {}", info.featureDataType)
+            data_type,
+            flags: FeatureFlag::from_bits_truncate(info.featureFlags),
+            visibility: FeatureVisibility::try_from(info.visibility)
+                                          .unwrap_or(FeatureVisibility::Unknown),
+            category: unsafe { pointer_to_option_str(info.category).map(str::to_string) },
+            display_name: unsafe {
+                pointer_to_option_str(info.displayName).map(str::to_string)
             },
-            flags: FeatureFlag::from_bits_truncate(info.featureFlags)
+            unit: unsafe { pointer_to_option_str(info.unit).map(str::to_string) },
+            tooltip: unsafe { pointer_to_option_str(info.tooltip).map(str::to_string) },
+            description: unsafe {
+                pointer_to_option_str(info.description).map(str::to_string)
+            }
+        })
+    }
+}
+
+
+
+// A feature whose name and type have been looked up once, for repeated access. It
+// goes through the same checks as the camera's own get_feature and set_feature.
+pub struct Feature<'a> {
+    camera: &'a Camera,
+    name: CString,
+    data_type: u32,
+    feature_type: FeatureType
+}
+
+impl<'a> Feature<'a> {
+    pub(crate) fn new(camera: &'a Camera, name: &str) -> Result<Self> {
+        camera.check_open()?;
+
+        let name = CString::new(name).expect("name cannot have internal zeros");
+        let mut info = VmbFeatureInfo_t::default();
+
+        vmbcall!(
+            VmbFeatureInfoQuery,
+            camera.handle(), name.as_ptr(), &mut info, FEATURE_INFO_SIZE
+        )?;
+
+        let data_type = info.featureDataType;
+        let feature_type = FeatureType::try_from(data_type)
+                                       .map_err(|_| Error::UnknownFeatureType(data_type))?;
+
+        Ok(Self { camera, name, data_type, feature_type })
+    }
+
+    pub fn name(&self) -> &str {
+        // This was made from a &str in the first place, so it must be valid
+        self.name.to_str().unwrap_or_default()
+    }
+
+    pub fn feature_type(&self) -> FeatureType {
+        self.feature_type
+    }
+
+    // Enum values are copied out for good, as in Camera::get_feature
+    pub fn get(&self) -> Result<FeatureValue<'a>> {
+        self.camera.check_open()?;
+
+        match read_feature(self.camera.handle(), &self.name, self.data_type)? {
+            FeatureValue::Enum(v) => Ok(FeatureValue::Enum(intern(v))),
+            value => Ok(value)
         }
     }
+
+    pub fn set(&self, value: FeatureValue) -> Result<()> {
+        self.camera.check_write_access()?;
+
+        if value.feature_type() != self.feature_type { return Err(Error::WrongType) }
+
+        write_feature(self.camera.handle(), &self.name, value)
+    }
 }
 
 
@@ -109,6 +268,174 @@ pub trait HasFeatures {
     fn run_command(&self, name: &str) -> Result<()>;
     fn is_command_done(&self, name: &str) -> Result<bool>;
 
+    // These query Vimba for more than a feature's value. They have defaults so that
+    // implementors outside this crate don't have to provide them all.
+    fn get_feature_int_range(&self, _name: &str) -> Result<(i64, i64)> {
+        Err(Error::NotImplemented)
+    }
+
+    fn get_feature_int_increment(&self, _name: &str) -> Result<i64> {
+        Err(Error::NotImplemented)
+    }
+
+    fn get_feature_float_range(&self, _name: &str) -> Result<(f64, f64)> {
+        Err(Error::NotImplemented)
+    }
+
+    fn get_feature_float_increment(&self, _name: &str) -> Result<Option<f64>> {
+        Err(Error::NotImplemented)
+    }
+
+    fn list_enum_entries(&self, _name: &str) -> Result<Vec<String>> {
+        Err(Error::NotImplemented)
+    }
+
+    fn is_enum_entry_available(&self, _name: &str, _entry: &str) -> Result<bool> {
+        Err(Error::NotImplemented)
+    }
+
+    fn feature_flags(&self, _name: &str) -> Result<FeatureFlag> {
+        Err(Error::NotImplemented)
+    }
+
+    fn get_feature_raw_length(&self, _name: &str) -> Result<usize> {
+        Err(Error::NotImplemented)
+    }
+
+    // Reads a raw feature into an existing buffer, giving how many bytes were
+    // written. The buffer should be at least get_feature_raw_length long.
+    fn read_feature_raw_into(&self, _name: &str, _buf: &mut [u8]) -> Result<usize> {
+        Err(Error::NotImplemented)
+    }
+
+    fn is_readable(&self, name: &str) -> Result<bool> {
+        Ok(self.feature_flags(name)?.contains(FeatureFlag::READ))
+    }
+
+    fn is_writable(&self, name: &str) -> Result<bool> {
+        Ok(self.feature_flags(name)?.contains(FeatureFlag::WRITE))
+    }
+
+    // Sets each feature in turn, stopping at the first failure. The error says which
+    // feature failed, and every feature before it will have been set.
+    fn set_features(&self, values: &[(&str, FeatureValue)]) -> Result<()> {
+        for (index, (name, value)) in values.iter().enumerate() {
+            self.set_feature(name, value.clone()).map_err(|e| Error::SetFeatures {
+                index,
+                name: name.to_string(),
+                error: Box::new(e)
+            })?;
+        }
+
+        Ok(())
+    }
+
+    // Reads every readable feature, leaving out commands and any feature that
+    // can't be read at the moment
+    fn snapshot(&self) -> Result<HashMap<String, FeatureValue<'_>>> {
+        let mut snapshot = HashMap::new();
+
+        for info in self.list_features()? {
+            let readable = info.flags.contains(FeatureFlag::READ);
+            let has_value = !matches!(info.data_type, FeatureType::Command | FeatureType::None);
+
+            if !(readable && has_value) { continue }
+
+            if let Ok(value) = self.get_feature(&info.name) {
+                snapshot.insert(info.name, value);
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    // Every feature alongside its value, if it has one that can be read right now.
+    // Meant for looking around a camera interactively, where snapshot is for code.
+    fn read_all(&self) -> Result<Vec<(FeatureInfo, Option<OwnedFeatureValue>)>> {
+        Ok(self.list_features()?.into_iter().map(|info| {
+            let value = match info.data_type {
+                FeatureType::Command | FeatureType::None => None,
+                _ => self.get_feature(&info.name).ok().map(FeatureValue::into_owned)
+            };
+
+            (info, value)
+        }).collect())
+    }
+
+    // Sets the features of a snapshot that are currently writable, in order of name
+    // so that failures are repeatable. Stops at the first failure like set_features.
+    fn restore(&self, snapshot: &HashMap<String, FeatureValue>) -> Result<()> {
+        let mut values: Vec<_> = snapshot.iter()
+            .filter(|(name, _)| self.is_writable(name) == Ok(true))
+            .map(|(name, value)| (name.as_str(), value.clone()))
+            .collect();
+
+        values.sort_by_key(|(name, _)| *name);
+
+        self.set_features(&values)
+    }
+
+    fn list_available_enum_entries(&self, name: &str) -> Result<Vec<String>> {
+        let mut available = vec![];
+
+        for entry in self.list_enum_entries(name)? {
+            if self.is_enum_entry_available(name, &entry)? { available.push(entry); }
+        }
+
+        Ok(available)
+    }
+
+    fn run_command_wait(&self, name: &str, timeout: Duration) -> Result<()> {
+        let start = Instant::now();
+
+        self.run_command(name)?;
+
+        while !self.is_command_done(name)? {
+            if start.elapsed() >= timeout { return Err(Error::Timeout) }
+
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        Ok(())
+    }
+
+    // Like run_command_wait, but while waiting, reads the progress feature and
+    // passes on its value whenever it changes. It can be an int or float feature.
+    fn run_command_with_progress<F>(
+        &self, name: &str, progress_feature: &str, timeout: Duration, mut on_progress: F
+    ) -> Result<()>
+    where F: FnMut(f64), Self: Sized {
+        let start = Instant::now();
+        let mut last = None;
+        let mut report = |value: Result<FeatureValue>| {
+            let progress = match value {
+                Ok(FeatureValue::Int(v)) => v as f64,
+                Ok(FeatureValue::Float(v)) => v,
+                _ => return
+            };
+
+            if last != Some(progress) {
+                last = Some(progress);
+                on_progress(progress);
+            }
+        };
+
+        self.run_command(name)?;
+
+        while !self.is_command_done(name)? {
+            report(self.get_feature(progress_feature));
+
+            if start.elapsed() >= timeout { return Err(Error::Timeout) }
+
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        // The final value may have been missed in between polls
+        report(self.get_feature(progress_feature));
+
+        Ok(())
+    }
+
     fn set_feature_int(&self, name: &str, v: i64) -> Result<()> {
         self.set_feature(name, FeatureValue::Int(v))
     }
@@ -121,6 +448,22 @@ pub trait HasFeatures {
         self.set_feature(name, FeatureValue::Enum(v))
     }
 
+    // Like set_feature_enum, but checks the entry is currently available first. If
+    // it isn't, the error lists the ones that are, rather than Vimba's InvalidValue.
+    fn set_feature_enum_checked(&self, name: &str, v: &str) -> Result<()> {
+        let available = self.list_available_enum_entries(name)?;
+
+        if !available.iter().any(|entry| entry == v) {
+            return Err(Error::UnavailableEnumEntry {
+                name: name.to_string(),
+                value: v.to_string(),
+                available
+            })
+        }
+
+        self.set_feature_enum(name, v)
+    }
+
     fn set_feature_string(&self, name: &str, v: &str) -> Result<()> {
         self.set_feature(name, FeatureValue::String(v.to_string()))
     }
@@ -134,166 +477,287 @@ pub trait HasFeatures {
     }
 
     fn get_feature_int(&self, name: &str) -> Result<i64> {
-        Ok(self.get_feature(name)?.into_int().unwrap())
+        self.get_feature(name)?.into_int().map_err(|_| Error::WrongType)
     }
 
     fn get_feature_float(&self, name: &str) -> Result<f64> {
-        Ok(self.get_feature(name)?.into_float().unwrap())
+        self.get_feature(name)?.into_float().map_err(|_| Error::WrongType)
     }
 
     fn get_feature_enum(&self, name: &str) -> Result<&str> {
-        Ok(self.get_feature(name)?.into_enum().unwrap())
+        self.get_feature(name)?.into_enum().map_err(|_| Error::WrongType)
     }
 
     fn get_feature_string(&self, name: &str) -> Result<String> {
-        Ok(self.get_feature(name)?.into_string().unwrap())
+        self.get_feature(name)?.into_string().map_err(|_| Error::WrongType)
     }
 
     fn get_feature_bool(&self, name: &str) -> Result<bool> {
-        Ok(self.get_feature(name)?.into_bool().unwrap())
+        self.get_feature(name)?.into_bool().map_err(|_| Error::WrongType)
     }
 
     fn get_feature_raw(&self, name: &str) -> Result<Vec<u8>> {
-        Ok(self.get_feature(name)?.into_raw().unwrap())
+        self.get_feature(name)?.into_raw().map_err(|_| Error::WrongType)
     }
 }
 
 impl HasFeatures for VmbHandle_t {
     fn get_feature(&self, name: &str) -> Result<FeatureValue> {
-        use FeatureValue::*;
-        use VmbFeatureDataType::*;
+        read_feature_by_name(*self, name)
+    }
 
-        let name = CString::new(name).expect("name cannot have internal zeros");
-        let name_ptr = name.as_ptr();
-        let mut info = VmbFeatureInfo_t::default();
+    fn set_feature(&self, name: &str, value: FeatureValue) -> Result<()> {
+        let name_cstr = CString::new(name).expect("name cannot have internal zeros");
 
-        vmbcall!(VmbFeatureInfoQuery, *self, name_ptr, &mut info, FEATURE_INFO_SIZE)?;
+        write_feature(*self, &name_cstr, value)
+    }
+
+    fn list_features(&self) -> Result<Vec<FeatureInfo>> {
+        let features = list_with_retry(|features, len, found| vmbcall!(
+            VmbFeaturesList,
+            *self, features, len, found, FEATURE_INFO_SIZE
+        ))?;
 
-        match info.featureDataType {
-            VmbFeatureDataInt => {
-                let mut v: i64 = 0;
-                vmbcall!(VmbFeatureIntGet, *self, name_ptr, &mut v)?;
+        features.into_iter().map(FeatureInfo::from_c_struct).collect()
+    }
+    
+    fn run_command(&self, name: &str) -> Result<()> {
+        let name_cstr = CString::new(name).expect("name cannot have internal zeros");
+        let name_ptr = name_cstr.as_ptr();
 
-                Ok(Int(v))
-            },
-            VmbFeatureDataFloat => {
-                let mut v: f64 = 0.0;
-                vmbcall!(VmbFeatureFloatGet, *self, name_ptr, &mut v)?;
-                
-                Ok(Float(v))
-            },
-            VmbFeatureDataEnum => {
-                let mut v: *const i8 = ptr::null();
-                vmbcall!(VmbFeatureEnumGet, *self, name_ptr, &mut v)?; 
-                let cstr = unsafe { CStr::from_ptr(v) };
+        vmbcall!(VmbFeatureCommandRun, *self, name_ptr)
+    }
+    
+    fn is_command_done(&self, name: &str) -> Result<bool> {
+        let name_cstr = CString::new(name).expect("name cannot have internal zeros");
+        let name_ptr = name_cstr.as_ptr();
+        let mut done: i8 = 0;
 
-                Ok(Enum(cstr.to_str().unwrap()))
-            },
-            VmbFeatureDataString => {
-                let mut len: u32 = 0;
+        vmbcall!(VmbFeatureCommandIsDone, *self, name_ptr, &mut done)?;
 
-                vmbcall!(
-                    VmbFeatureStringGet,
-                    *self, name_ptr, ptr::null_mut(), 0, &mut len
-                )?;
+        Ok(done != 0)
+    }
 
-                let mut buf = vec![0u8; len as usize];
+    fn get_feature_int_range(&self, name: &str) -> Result<(i64, i64)> {
+        let name_cstr = CString::new(name).expect("name cannot have internal zeros");
+        let name_ptr = name_cstr.as_ptr();
+        let (mut min, mut max): (i64, i64) = (0, 0);
 
-                vmbcall!(
-                    VmbFeatureStringGet,
-                    *self, name_ptr, buf.as_mut_ptr() as *mut i8, len, ptr::null_mut()
-                )?;
+        // Vimba checks the feature type itself, returning WrongType if not an int
+        vmbcall!(VmbFeatureIntRangeQuery, *self, name_ptr, &mut min, &mut max)?;
 
-                match std::string::String::from_utf8(buf) {
-                    Ok(s) => Ok(String(s)),
-                    Err(_) => panic!("")
-                }
-            },
-            VmbFeatureDataBool => {
-                let mut v: VmbBool_t = 0;
-                vmbcall!(VmbFeatureBoolGet, *self, name_ptr, &mut v)?;
-                
-                Ok(Bool(v != 0))
-            },
-            VmbFeatureDataRaw => {
-                let mut len: u32 = 0;
-                vmbcall!(VmbFeatureRawLengthQuery, *self, name_ptr, &mut len)?;
+        Ok((min, max))
+    }
 
-                let mut buf = vec![0u8; len as usize];
-                let mut filled: u32 = 0;
+    fn get_feature_int_increment(&self, name: &str) -> Result<i64> {
+        let name_cstr = CString::new(name).expect("name cannot have internal zeros");
+        let name_ptr = name_cstr.as_ptr();
+        let mut increment: i64 = 0;
 
-                vmbcall!(
-                    VmbFeatureRawGet,
-                    *self, name_ptr, buf.as_mut_ptr() as *mut i8, len, &mut filled
-                )?;
+        vmbcall!(VmbFeatureIntIncrementQuery, *self, name_ptr, &mut increment)?;
 
-                Ok(Raw(buf))
-            },
-            _ => panic!("Unknown Vimba feature code {}", info.featureDataType)
-        }
+        Ok(increment)
     }
 
-    fn set_feature(&self, name: &str, value: FeatureValue) -> Result<()> {
-        use FeatureValue::*;
+    fn get_feature_float_range(&self, name: &str) -> Result<(f64, f64)> {
+        let name_cstr = CString::new(name).expect("name cannot have internal zeros");
+        let name_ptr = name_cstr.as_ptr();
+        let (mut min, mut max): (f64, f64) = (0.0, 0.0);
+
+        vmbcall!(VmbFeatureFloatRangeQuery, *self, name_ptr, &mut min, &mut max)?;
+
+        Ok((min, max))
+    }
 
+    fn get_feature_float_increment(&self, name: &str) -> Result<Option<f64>> {
         let name_cstr = CString::new(name).expect("name cannot have internal zeros");
         let name_ptr = name_cstr.as_ptr();
+        let mut has_increment: VmbBool_t = 0;
+        let mut increment: f64 = 0.0;
 
-        match value {
-            Int(v) => vmbcall!(VmbFeatureIntSet, *self, name_ptr, v),
-            Float(v) => vmbcall!(VmbFeatureFloatSet, *self, name_ptr, v),
-            Enum(v) => {
-                let v = CString::new(v).expect("value cannot have internal zeros");
-                
-                vmbcall!(VmbFeatureEnumSet, *self, name_ptr, v.as_ptr())
-            },
-            String(v) => {
-                let v = CString::new(v).expect("value cannot have internal zeros");
-                
-                vmbcall!(VmbFeatureStringSet, *self, name_ptr, v.as_ptr())
-            },
-            Bool(v) => vmbcall!(VmbFeatureBoolSet, *self, name_ptr, v as i8),
-            Raw(v) => {
-                let ptr = v.as_ptr() as *const i8;
-                let len = v.len() as u32;
+        vmbcall!(
+            VmbFeatureFloatIncrementQuery,
+            *self, name_ptr, &mut has_increment, &mut increment
+        )?;
 
-                vmbcall!(VmbFeatureRawSet, *self, name_ptr, ptr, len)
-            }
-        }
+        // Features without an increment can take any value within their range
+        Ok(if has_increment != 0 { Some(increment) } else { None })
     }
 
-    fn list_features(&self) -> Result<Vec<FeatureInfo>> {
+    fn list_enum_entries(&self, name: &str) -> Result<Vec<String>> {
+        let name_cstr = CString::new(name).expect("name cannot have internal zeros");
+        let name_ptr = name_cstr.as_ptr();
         let mut n: u32 = 0;
-        
+
+        vmbcall!(VmbFeatureEnumRangeQuery, *self, name_ptr, ptr::null_mut(), 0, &mut n)?;
+
+        let mut entries: Vec<*const i8> = vec![ptr::null(); n as usize];
+
         vmbcall!(
-            VmbFeaturesList,
-            *self, ptr::null_mut(), 0, &mut n, FEATURE_INFO_SIZE
+            VmbFeatureEnumRangeQuery,
+            *self, name_ptr, entries.as_mut_ptr(), n, &mut n
         )?;
 
-        let mut features = vec![VmbFeatureInfo_t::default(); n as usize];
-        
+        entries.truncate(n as usize);
+
+        Ok(entries.into_iter().map(|p| unsafe { pointer_to_str(p).to_string() }).collect())
+    }
+
+    fn is_enum_entry_available(&self, name: &str, entry: &str) -> Result<bool> {
+        let name_cstr = CString::new(name).expect("name cannot have internal zeros");
+        let entry_cstr = CString::new(entry).expect("entry cannot have internal zeros");
+        let mut available: VmbBool_t = 0;
+
         vmbcall!(
-            VmbFeaturesList,
-            *self, features.as_mut_ptr(), n, ptr::null_mut(), FEATURE_INFO_SIZE
+            VmbFeatureEnumIsAvailable,
+            *self, name_cstr.as_ptr(), entry_cstr.as_ptr(), &mut available
         )?;
 
-        Ok(features.into_iter().map(FeatureInfo::from_c_struct).collect())
+        Ok(available != 0)
     }
-    
-    fn run_command(&self, name: &str) -> Result<()> {
+
+    fn feature_flags(&self, name: &str) -> Result<FeatureFlag> {
         let name_cstr = CString::new(name).expect("name cannot have internal zeros");
         let name_ptr = name_cstr.as_ptr();
+        let mut info = VmbFeatureInfo_t::default();
 
-        vmbcall!(VmbFeatureCommandRun, *self, name_ptr)
+        vmbcall!(VmbFeatureInfoQuery, *self, name_ptr, &mut info, FEATURE_INFO_SIZE)?;
+
+        Ok(FeatureFlag::from_bits_truncate(info.featureFlags))
     }
-    
-    fn is_command_done(&self, name: &str) -> Result<bool> {
+
+    fn get_feature_raw_length(&self, name: &str) -> Result<usize> {
         let name_cstr = CString::new(name).expect("name cannot have internal zeros");
-        let name_ptr = name_cstr.as_ptr();
-        let mut done: i8 = 0;
+        let mut len: u32 = 0;
 
-        vmbcall!(VmbFeatureCommandIsDone, *self, name_ptr, &mut done)?;
+        vmbcall!(VmbFeatureRawLengthQuery, *self, name_cstr.as_ptr(), &mut len)?;
 
-        Ok(done != 0)
+        Ok(len as usize)
+    }
+
+    fn read_feature_raw_into(&self, name: &str, buf: &mut [u8]) -> Result<usize> {
+        let name_cstr = CString::new(name).expect("name cannot have internal zeros");
+        let len = u32::try_from(buf.len()).map_err(|_| Error::BadParameter)?;
+        let mut filled: u32 = 0;
+
+        vmbcall!(
+            VmbFeatureRawGet,
+            *self, name_cstr.as_ptr(), buf.as_mut_ptr() as *mut i8, len, &mut filled
+        )?;
+
+        Ok(filled as usize)
+    }
+}
+
+
+
+// Enum values borrow from Vimba, and are only valid while the handle stays open
+pub(crate) fn read_feature_by_name<'a>(handle: VmbHandle_t, name: &str)
+-> Result<FeatureValue<'a>> {
+    let name = CString::new(name).expect("name cannot have internal zeros");
+    let mut info = VmbFeatureInfo_t::default();
+
+    vmbcall!(VmbFeatureInfoQuery, handle, name.as_ptr(), &mut info, FEATURE_INFO_SIZE)?;
+
+    read_feature(handle, &name, info.featureDataType)
+}
+
+// Reads a feature whose type is already known, saving a VmbFeatureInfoQuery
+fn read_feature<'a>(handle: VmbHandle_t, name: &CStr, data_type: u32)
+-> Result<FeatureValue<'a>> {
+    use FeatureValue::*;
+    use VmbFeatureDataType::*;
+
+    let name_ptr = name.as_ptr();
+
+    match data_type {
+        VmbFeatureDataInt => {
+            let mut v: i64 = 0;
+            vmbcall!(VmbFeatureIntGet, handle, name_ptr, &mut v)?;
+
+            Ok(Int(v))
+        },
+        VmbFeatureDataFloat => {
+            let mut v: f64 = 0.0;
+            vmbcall!(VmbFeatureFloatGet, handle, name_ptr, &mut v)?;
+            
+            Ok(Float(v))
+        },
+        VmbFeatureDataEnum => {
+            let mut v: *const i8 = ptr::null();
+            vmbcall!(VmbFeatureEnumGet, handle, name_ptr, &mut v)?; 
+            let cstr = unsafe { CStr::from_ptr(v) };
+
+            cstr.to_str().map(Enum).map_err(|_| Error::BadString)
+        },
+        VmbFeatureDataString => {
+            let mut len: u32 = 0;
+
+            vmbcall!(
+                VmbFeatureStringGet,
+                handle, name_ptr, ptr::null_mut(), 0, &mut len
+            )?;
+
+            let mut buf = vec![0u8; len as usize];
+
+            vmbcall!(
+                VmbFeatureStringGet,
+                handle, name_ptr, buf.as_mut_ptr() as *mut i8, len, ptr::null_mut()
+            )?;
+
+            // The length Vimba gives includes the null terminator
+            buf.truncate(buf.iter().position(|&b| b == 0).unwrap_or(buf.len()));
+
+            std::string::String::from_utf8(buf).map(String).map_err(|_| Error::BadString)
+        },
+        VmbFeatureDataBool => {
+            let mut v: VmbBool_t = 0;
+            vmbcall!(VmbFeatureBoolGet, handle, name_ptr, &mut v)?;
+            
+            Ok(Bool(v != 0))
+        },
+        VmbFeatureDataRaw => {
+            let mut len: u32 = 0;
+            vmbcall!(VmbFeatureRawLengthQuery, handle, name_ptr, &mut len)?;
+
+            let mut buf = vec![0u8; len as usize];
+            let mut filled: u32 = 0;
+
+            vmbcall!(
+                VmbFeatureRawGet,
+                handle, name_ptr, buf.as_mut_ptr() as *mut i8, len, &mut filled
+            )?;
+
+            Ok(Raw(buf))
+        },
+        t => Err(Error::UnknownFeatureType(t))
+    }
+}
+
+fn write_feature(handle: VmbHandle_t, name: &CStr, value: FeatureValue) -> Result<()> {
+    use FeatureValue::*;
+
+    let name_ptr = name.as_ptr();
+
+    match value {
+        Int(v) => vmbcall!(VmbFeatureIntSet, handle, name_ptr, v),
+        Float(v) => vmbcall!(VmbFeatureFloatSet, handle, name_ptr, v),
+        Enum(v) => {
+            let v = CString::new(v).expect("value cannot have internal zeros");
+            
+            vmbcall!(VmbFeatureEnumSet, handle, name_ptr, v.as_ptr())
+        },
+        String(v) => {
+            let v = CString::new(v).expect("value cannot have internal zeros");
+            
+            vmbcall!(VmbFeatureStringSet, handle, name_ptr, v.as_ptr())
+        },
+        Bool(v) => vmbcall!(VmbFeatureBoolSet, handle, name_ptr, v as i8),
+        Raw(v) => {
+            let ptr = v.as_ptr() as *const i8;
+            let len = v.len() as u32;
+
+            vmbcall!(VmbFeatureRawSet, handle, name_ptr, ptr, len)
+        }
     }
 }
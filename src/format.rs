@@ -1,7 +1,10 @@
 #![allow(non_camel_case_types, dead_code)]
 
 use crate::vimba_sys::{VmbPixelFormatType::*, VmbPixelType::*};
+use crate::error::Error;
 use num_derive::FromPrimitive;
+use std::fmt;
+use std::str::FromStr;
 
 
 
@@ -93,6 +96,28 @@ pub enum PixelFormat {
     YCbCr8_CbYCr = VmbPixelFormatYCbCr8_CbYCr
 }
 
+// The colour filter arrangement of a Bayer format, named after the first 2 pixels
+// of the first row, with the second row being the other 2 colours
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BayerPattern {
+    GR, RG, GB, BG
+}
+
+impl BayerPattern {
+    // Position of the red pixel within each 2x2 tile of the colour filter array.
+    // Blue is always on the opposite corner, and green fills the rest.
+    pub fn red_position(&self) -> (usize, usize) {
+        match self {
+            Self::RG => (0, 0),
+            Self::GR => (1, 0),
+            Self::GB => (0, 1),
+            Self::BG => (1, 1)
+        }
+    }
+}
+
+
+
 impl Default for PixelFormat {
     fn default() -> Self {
         Self::Mono8
@@ -100,12 +125,39 @@ impl Default for PixelFormat {
 }
 
 impl PixelFormat {
+    // The number of bits each pixel actually occupies in the image buffer, padding
+    // included. This comes straight from the format code, where the PFNC defines it
+    // to be the packed density, so e.g. Mono10 is 16 but Mono10p is 10. For
+    // chroma subsampled formats it's an average, e.g. 12 for Yuv411.
     pub fn bits_per_pixel(&self) -> usize {
         const SHIFT: u32 = FORMAT_BIT_DEPTH_MASK.trailing_zeros();
 
         ((*self as u32 & FORMAT_BIT_DEPTH_MASK) >> SHIFT) as usize
     }
 
+    pub fn row_stride(&self, width: usize) -> usize {
+        // Packed rows are rounded up to a whole number of bytes
+        (width*self.bits_per_pixel()).div_ceil(8)
+    }
+
+    pub fn image_buffer_size(&self, width: usize, height: usize) -> usize {
+        self.row_stride(width)*height
+    }
+
+    // Whether samples are packed together with no padding, so that they don't all
+    // start on a byte boundary.
+    pub fn is_packed(&self) -> bool {
+        use PixelFormat::*;
+
+        matches!(
+            self,
+            Mono10p | Mono12Packed | Mono12p
+            | BayerGR10p | BayerRG10p | BayerGB10p | BayerBG10p
+            | BayerGR12p | BayerRG12p | BayerGB12p | BayerBG12p
+            | BayerGR12Packed | BayerRG12Packed | BayerGB12Packed | BayerBG12Packed
+        )
+    }
+
     pub fn is_color(&self) -> bool {
         *self as u32 & VmbPixelColor > 0
     }
@@ -128,6 +180,8 @@ impl PixelFormat {
         }
     }
 
+    // The number of significant bits in each sample, regardless of how the samples
+    // are laid out in the buffer.
     pub fn bits_per_channel(&self) -> usize {
         use PixelFormat::*;
 
@@ -157,6 +211,36 @@ impl PixelFormat {
         }
     }
 
+    pub fn is_bayer(&self) -> bool {
+        self.bayer_pattern().is_some()
+    }
+
+    pub fn bayer_pattern(&self) -> Option<BayerPattern> {
+        use PixelFormat::*;
+
+        match self {
+            BayerGR8 | BayerGR10 | BayerGR10p | BayerGR12 | BayerGR12p
+            | BayerGR12Packed | BayerGR16 => Some(BayerPattern::GR),
+            BayerRG8 | BayerRG10 | BayerRG10p | BayerRG12 | BayerRG12p
+            | BayerRG12Packed | BayerRG16 => Some(BayerPattern::RG),
+            BayerGB8 | BayerGB10 | BayerGB10p | BayerGB12 | BayerGB12p
+            | BayerGB12Packed | BayerGB16 => Some(BayerPattern::GB),
+            BayerBG8 | BayerBG10 | BayerBG10p | BayerBG12 | BayerBG12p
+            | BayerBG12Packed | BayerBG16 => Some(BayerPattern::BG),
+            _ => None
+        }
+    }
+
+    // The 8-bit Bayer format with the same colour filter phase as this one
+    pub(crate) fn bayer_8bit_equivalent(&self) -> Option<PixelFormat> {
+        Some(match self.bayer_pattern()? {
+            BayerPattern::GR => PixelFormat::BayerGR8,
+            BayerPattern::RG => PixelFormat::BayerRG8,
+            BayerPattern::GB => PixelFormat::BayerGB8,
+            BayerPattern::BG => PixelFormat::BayerBG8
+        })
+    }
+
     pub fn unpack_to_u16(&self, raw: &[u8]) -> Option<Vec<u16>> {
         use PixelFormat::*;
 
@@ -182,24 +266,15 @@ impl PixelFormat {
                 }
             },
 
-            Mono10p
-            | BayerGR10p | BayerRG10p | BayerGB10p | BayerBG10p
-            | BayerGR12p | BayerRG12p | BayerGB12p | BayerBG12p 
+            Mono12Packed
             | BayerGR12Packed | BayerRG12Packed | BayerGB12Packed | BayerBG12Packed
-            => {
-                let channel_bits = self.bits_per_channel();
-                out.resize((raw.len()*8)/channel_bits, 0);
+            => out = unpack_12_packed(raw),
 
-                for (i, x) in out.iter_mut().enumerate() {
-                    let start_bit = i*channel_bits;
-                    let start_byte = start_bit/8;
-                    let rem_bits = start_bit % 8;
-                    let first = raw[start_byte];
-                    let next = raw[start_byte+1];
+            Mono10p | BayerGR10p | BayerRG10p | BayerGB10p | BayerBG10p
+            => out = unpack_10p(raw),
 
-                    *x = u16::from_le_bytes([first, next]) >> rem_bits;
-                }
-            }
+            Mono12p | BayerGR12p | BayerRG12p | BayerGB12p | BayerBG12p
+            => out = unpack_12p(raw),
             
             // Everything else has a number of bits < 8 or >= 16
             _ => return None
@@ -207,4 +282,576 @@ impl PixelFormat {
 
         Some(out)
     }
+
+    // A lazy version of unpack_to_u16, which also widens 8-bit samples rather than
+    // refusing them. Nothing is allocated, so it's fine for a quick pass over the
+    // image like a histogram.
+    pub fn iter_u16<'a>(&self, raw: &'a [u8]) -> Box<dyn Iterator<Item = u16> + 'a> {
+        use PixelFormat::*;
+
+        let bits = self.bits_per_channel();
+
+        match self {
+            Mono12Packed
+            | BayerGR12Packed | BayerRG12Packed | BayerGB12Packed | BayerBG12Packed
+            => Box::new(raw.chunks_exact(3).flat_map(|b| {
+                unpack_12_packed_pair([b[0], b[1], b[2]])
+            })),
+            _ if self.is_packed() => Box::new((0..raw.len()*8/bits).map(move |i| {
+                unpack_lsb_first_at(raw, bits, i)
+            })),
+            _ if bits == 8 => Box::new(raw.iter().map(|&x| x as u16)),
+            _ => Box::new(raw.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])))
+        }
+    }
+
+    pub fn unpack_to_u8(&self, raw: &[u8]) -> Option<Vec<u8>> {
+        let channel_bits = self.bits_per_channel();
+
+        // Already 1 byte per component, so there's nothing to unpack
+        if channel_bits == 8 { return Some(raw.to_vec()) }
+
+        // Otherwise unpack as normal and keep only the most significant 8 bits
+        let shift = channel_bits - 8;
+        let unpacked = self.unpack_to_u16(raw)?;
+
+        Some(unpacked.into_iter().map(|x| (x >> shift) as u8).collect())
+    }
+
+    pub fn debayer_to_rgb8(&self, raw: &[u8], width: usize, height: usize)
+    -> Option<Vec<u8>> {
+        // Only 8-bit Bayer data is demosaiced directly
+        if self.bits_per_pixel() != 8 { return None }
+
+        let (red_x, red_y) = self.bayer_pattern()?.red_position();
+
+        if raw.len() < width*height { return None }
+
+        // 0 = red, 1 = green, 2 = blue, matching the order of the output channels
+        let channel_at = |x: usize, y: usize| {
+            if (x & 1, y & 1) == (red_x, red_y) { 0 }
+            else if (x & 1, y & 1) == (red_x ^ 1, red_y ^ 1) { 2 }
+            else { 1 }
+        };
+
+        let mut out = vec![0u8; width*height*3];
+
+        for y in 0..height {
+            for x in 0..width {
+                let own = channel_at(x, y);
+                let mut sums = [0u32; 3];
+                let mut counts = [0u32; 3];
+
+                // Bilinear interpolation is just the mean of the neighbours of each
+                // colour in the surrounding 3x3 window. Depending on the phase that
+                // gives 2 or 4 neighbours, and fewer at the edges of the image.
+                for ny in y.saturating_sub(1)..(y+2).min(height) {
+                    for nx in x.saturating_sub(1)..(x+2).min(width) {
+                        let c = channel_at(nx, ny);
+                        sums[c] += raw[ny*width + nx] as u32;
+                        counts[c] += 1;
+                    }
+                }
+
+                let pixel = &mut out[(y*width + x)*3..][..3];
+
+                for (c, v) in pixel.iter_mut().enumerate() {
+                    let mean = (sums[c] + counts[c]/2).checked_div(counts[c]);
+
+                    *v = if c == own { raw[y*width + x] }
+                         else { mean.unwrap_or(0) as u8 };
+                }
+            }
+        }
+
+        Some(out)
+    }
+
+    pub fn yuv_to_rgb8(&self, raw: &[u8], width: usize, height: usize)
+    -> Option<Vec<u8>> {
+        use PixelFormat::*;
+
+        // The Vimba YUV formats and their PFNC YCbCr counterparts share a layout.
+        // Each group of bytes holds one Cb and one Cr sample, which are shared by
+        // all the luma samples in the group, given here by their byte offsets.
+        let (cb_idx, cr_idx, y_idx): (usize, usize, &[usize]) = match self {
+            Yuv444 | YCbCr8_CbYCr => (0, 2, &[1]),
+            Yuv422 | YCbCr422_8_CbYCrY => (0, 2, &[1, 3]),
+            Yuv411 | YCbCr411_8_CbYYCrYY => (0, 3, &[1, 2, 4, 5]),
+            _ => return None
+        };
+
+        let sub = y_idx.len();
+        let group_len = sub + 2;
+        let groups_per_row = width/sub;
+        let row_len = groups_per_row*group_len;
+
+        if !width.is_multiple_of(sub) || raw.len() < row_len*height { return None }
+
+        let mut out = vec![0u8; width*height*3];
+
+        if out.is_empty() { return Some(out) }
+
+        let rows = raw.chunks_exact(row_len).zip(out.chunks_exact_mut(width*3));
+
+        for (row, out_row) in rows {
+            let group = |g: usize| &row[g.min(groups_per_row - 1)*group_len..][..group_len];
+
+            for (x, pixel) in out_row.chunks_exact_mut(3).enumerate() {
+                let (g, phase) = (x/sub, x % sub);
+                let (this, next) = (group(g), group(g + 1));
+
+                // The chroma is sited on the first pixel of each group, so the pixels
+                // in between are linearly interpolated towards the next group's.
+                let lerp = |i: usize| {
+                    let a = this[i] as f32;
+                    let b = next[i] as f32;
+
+                    a + (b - a)*(phase as f32)/(sub as f32) - 128.0
+                };
+
+                let y = this[y_idx[phase]] as f32;
+                let cb = lerp(cb_idx);
+                let cr = lerp(cr_idx);
+
+                // Full-range BT.601, as used by the PFNC YCbCr8 formats
+                let r = y + 1.402*cr;
+                let g = y - 0.344136*cb - 0.714136*cr;
+                let b = y + 1.772*cb;
+
+                pixel[0] = r.round().clamp(0.0, 255.0) as u8;
+                pixel[1] = g.round().clamp(0.0, 255.0) as u8;
+                pixel[2] = b.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+
+        Some(out)
+    }
+}
+
+// The GigE Vision packing stores the top 8 bits of each of a pair of pixels in the
+// first and last bytes, with the 2 remaining nibbles sharing the middle byte, low
+// nibble first. Any incomplete pair at the end is left out.
+pub fn unpack_12_packed(raw: &[u8]) -> Vec<u16> {
+    raw.chunks_exact(3).flat_map(|b| unpack_12_packed_pair([b[0], b[1], b[2]])).collect()
+}
+
+fn unpack_12_packed_pair(b: [u8; 3]) -> [u16; 2] {
+    [
+        ((b[0] as u16) << 4) | (b[1] & 0x0F) as u16,
+        ((b[2] as u16) << 4) | (b[1] >> 4) as u16
+    ]
+}
+
+pub fn unpack_10p(raw: &[u8]) -> Vec<u16> {
+    unpack_lsb_first(raw, 10)
+}
+
+pub fn unpack_12p(raw: &[u8]) -> Vec<u16> {
+    unpack_lsb_first(raw, 12)
+}
+
+// The PFNC "p" packings are one continuous stream of bits, least significant first,
+// so each pixel starts where the last one ended. For these bit depths a pixel is
+// always contained in the 2 bytes from the one it starts in.
+fn unpack_lsb_first(raw: &[u8], bits: usize) -> Vec<u16> {
+    (0..raw.len()*8/bits).map(|i| unpack_lsb_first_at(raw, bits, i)).collect()
+}
+
+fn unpack_lsb_first_at(raw: &[u8], bits: usize, i: usize) -> u16 {
+    let start_bit = i*bits;
+    let start_byte = start_bit/8;
+    let pair = u16::from_le_bytes([raw[start_byte], raw[start_byte + 1]]);
+
+    (pair >> (start_bit % 8)) & ((1u16 << bits) - 1)
+}
+
+
+// The PFNC name of every format, which is what the PixelFormat feature uses. Both
+// Display and FromStr go by this, so the two can't disagree.
+const NAMES: [(PixelFormat, &str); 62] = {
+    use PixelFormat::*;
+
+    [
+        (Mono8, "Mono8"),
+        (Mono10, "Mono10"),
+        (Mono10p, "Mono10p"),
+        (Mono12, "Mono12"),
+        (Mono12Packed, "Mono12Packed"),
+        (Mono12p, "Mono12p"),
+        (Mono14, "Mono14"),
+        (Mono16, "Mono16"),
+        (BayerGR8, "BayerGR8"),
+        (BayerRG8, "BayerRG8"),
+        (BayerGB8, "BayerGB8"),
+        (BayerBG8, "BayerBG8"),
+        (BayerGR10, "BayerGR10"),
+        (BayerRG10, "BayerRG10"),
+        (BayerGB10, "BayerGB10"),
+        (BayerBG10, "BayerBG10"),
+        (BayerGR12, "BayerGR12"),
+        (BayerRG12, "BayerRG12"),
+        (BayerGB12, "BayerGB12"),
+        (BayerBG12, "BayerBG12"),
+        (BayerGR12Packed, "BayerGR12Packed"),
+        (BayerRG12Packed, "BayerRG12Packed"),
+        (BayerGB12Packed, "BayerGB12Packed"),
+        (BayerBG12Packed, "BayerBG12Packed"),
+        (BayerGR10p, "BayerGR10p"),
+        (BayerRG10p, "BayerRG10p"),
+        (BayerGB10p, "BayerGB10p"),
+        (BayerBG10p, "BayerBG10p"),
+        (BayerGR12p, "BayerGR12p"),
+        (BayerRG12p, "BayerRG12p"),
+        (BayerGB12p, "BayerGB12p"),
+        (BayerBG12p, "BayerBG12p"),
+        (BayerGR16, "BayerGR16"),
+        (BayerRG16, "BayerRG16"),
+        (BayerGB16, "BayerGB16"),
+        (BayerBG16, "BayerBG16"),
+        (Rgb8, "RGB8"),
+        (Bgr8, "BGR8"),
+        (Rgb10, "RGB10"),
+        (Bgr10, "BGR10"),
+        (Rgb12, "RGB12"),
+        (Bgr12, "BGR12"),
+        (Rgb14, "RGB14"),
+        (Bgr14, "BGR14"),
+        (Rgb16, "RGB16"),
+        (Bgr16, "BGR16"),
+        (Rgba8, "RGBa8"),
+        (Bgra8, "BGRa8"),
+        (Rgba10, "RGBa10"),
+        (Bgra10, "BGRa10"),
+        (Rgba12, "RGBa12"),
+        (Bgra12, "BGRa12"),
+        (Rgba14, "RGBa14"),
+        (Bgra14, "BGRa14"),
+        (Rgba16, "RGBa16"),
+        (Bgra16, "BGRa16"),
+        (Yuv411, "YUV411_8_UYYVYY"),
+        (Yuv422, "YUV422_8_UYVY"),
+        (Yuv444, "YUV8_UYV"),
+        (YCbCr411_8_CbYYCrYY, "YCbCr411_8_CbYYCrYY"),
+        (YCbCr422_8_CbYCrY, "YCbCr422_8_CbYCrY"),
+        (YCbCr8_CbYCr, "YCbCr8_CbYCr")
+    ]
+};
+
+// Older GigE cameras use the names from before the PFNC existed. These are only
+// parsed, never displayed.
+const LEGACY_NAMES: [(PixelFormat, &str); 12] = {
+    use PixelFormat::*;
+
+    [
+        (Rgb8, "RGB8Packed"),
+        (Bgr8, "BGR8Packed"),
+        (Rgba8, "RGBA8Packed"),
+        (Bgra8, "BGRA8Packed"),
+        (Rgb10, "RGB10Packed"),
+        (Bgr10, "BGR10Packed"),
+        (Rgb12, "RGB12Packed"),
+        (Bgr12, "BGR12Packed"),
+        (Rgb16, "RGB16Packed"),
+        (Yuv411, "YUV411Packed"),
+        (Yuv422, "YUV422Packed"),
+        (Yuv444, "YUV444Packed")
+    ]
+};
+
+impl fmt::Display for PixelFormat {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match NAMES.iter().find(|(format, _)| format == self) {
+            Some((_, name)) => fmt.write_str(name),
+            // Can't happen, as long as NAMES has every format
+            None => write!(fmt, "PixelFormat({:#010X})", *self as u32)
+        }
+    }
+}
+
+impl FromStr for PixelFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NAMES.iter().chain(&LEGACY_NAMES)
+            .find(|(_, name)| *name == s)
+            .map(|(format, _)| *format)
+            .ok_or(Error::UnknownPixelFormat)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::PixelFormat::*;
+
+    #[test]
+    fn packed_bits_per_pixel() {
+        assert_eq!(Mono10p.bits_per_pixel(), 10);
+        assert_eq!(Mono12p.bits_per_pixel(), 12);
+        assert_eq!(Mono12Packed.bits_per_pixel(), 12);
+        assert_eq!(Mono10.bits_per_pixel(), 16);
+        assert_eq!(Mono12.bits_per_pixel(), 16);
+
+        assert_eq!(Mono12Packed.bits_per_channel(), 12);
+        assert!(Mono12Packed.is_packed());
+        assert!(!Mono12.is_packed());
+    }
+
+    #[test]
+    fn packed_buffer_sizes() {
+        // 4 pixels of 10 bits fit exactly into 5 bytes
+        assert_eq!(Mono10p.row_stride(4), 5);
+        assert_eq!(Mono10p.image_buffer_size(640, 480), 384000);
+
+        // 2 pixels of 12 bits fit exactly into 3 bytes
+        assert_eq!(Mono12p.row_stride(2), 3);
+        assert_eq!(Mono12p.image_buffer_size(640, 480), 460800);
+        assert_eq!(Mono12Packed.row_stride(2), 3);
+        assert_eq!(Mono12Packed.image_buffer_size(640, 480), 460800);
+
+        // Partially filled bytes at the end of a row still count
+        assert_eq!(Mono12Packed.row_stride(3), 5);
+        assert_eq!(Mono10p.row_stride(3), 4);
+    }
+
+    #[test]
+    fn unpack_mono12packed() {
+        // Pixel 0 is 0xABC and pixel 1 is 0xEFD
+        let raw = [0xAB, 0xDC, 0xEF, 0x12, 0x43, 0x65];
+        let unpacked = Mono12Packed.unpack_to_u16(&raw).unwrap();
+
+        assert_eq!(unpacked, vec![0xABC, 0xEFD, 0x123, 0x654]);
+        assert_eq!(BayerRG12Packed.unpack_to_u16(&raw), Some(unpacked));
+    }
+
+    #[test]
+    fn unpack_p_formats() {
+        // Each pixel's bits carry straight on from the last one's
+        let raw = [0xFF, 0x07, 0xA0, 0x6A, 0x55];
+        let unpacked = vec![0x3FF, 0x001, 0x2AA, 0x155];
+
+        assert_eq!(super::unpack_10p(&raw), unpacked);
+        assert_eq!(Mono10p.unpack_to_u16(&raw), Some(unpacked));
+
+        let raw = [0xBC, 0x3A, 0x12];
+        let unpacked = vec![0xABC, 0x123];
+
+        assert_eq!(super::unpack_12p(&raw), unpacked);
+        assert_eq!(Mono12p.unpack_to_u16(&raw), Some(unpacked.clone()));
+        assert_eq!(BayerGB12p.unpack_to_u16(&raw), Some(unpacked));
+    }
+
+    #[test]
+    fn unpack_to_8_bits() {
+        // The same data as unpack_mono12packed and unpack_p_formats, keeping only the
+        // most significant 8 bits of each pixel
+        let raw = [0xAB, 0xDC, 0xEF, 0x12, 0x43, 0x65];
+        let unpacked = vec![0xAB, 0xEF, 0x12, 0x65];
+
+        assert_eq!(Mono12Packed.unpack_to_u8(&raw), Some(unpacked.clone()));
+        assert_eq!(BayerRG12Packed.unpack_to_u8(&raw), Some(unpacked));
+
+        let raw = [0xFF, 0x07, 0xA0, 0x6A, 0x55];
+
+        assert_eq!(Mono10p.unpack_to_u8(&raw), Some(vec![0xFF, 0x00, 0xAA, 0x55]));
+
+        let raw = [0xBC, 0x3A, 0x12];
+
+        assert_eq!(Mono12p.unpack_to_u8(&raw), Some(vec![0xAB, 0x12]));
+        assert_eq!(BayerGB12p.unpack_to_u8(&raw), Some(vec![0xAB, 0x12]));
+
+        // Unpacked formats just lose their low bits, and 8-bit ones are left as is
+        let raw = [0xBC, 0x0A, 0x23, 0x01];
+
+        assert_eq!(Mono12.unpack_to_u8(&raw), Some(vec![0xAB, 0x12]));
+        assert_eq!(Mono8.unpack_to_u8(&raw), Some(raw.to_vec()));
+    }
+
+    #[test]
+    fn lazy_unpacking() {
+        let raw = [0xAB, 0xDC, 0xEF, 0x12, 0x43, 0x65];
+
+        for format in [Mono8, Mono10p, Mono12Packed, Mono12p, Mono16] {
+            let unpacked: Vec<u16> = format.iter_u16(&raw).collect();
+
+            match format.unpack_to_u16(&raw) {
+                Some(expected) => assert_eq!(unpacked, expected, "{format}"),
+                None => assert_eq!(unpacked, raw.map(u16::from), "{format}")
+            }
+        }
+    }
+
+    #[test]
+    fn name_round_trip() {
+        let formats = [
+            Mono8,
+            Mono10,
+            Mono10p,
+            Mono12,
+            Mono12Packed,
+            Mono12p,
+            Mono14,
+            Mono16,
+            BayerGR8,
+            BayerRG8,
+            BayerGB8,
+            BayerBG8,
+            BayerGR10,
+            BayerRG10,
+            BayerGB10,
+            BayerBG10,
+            BayerGR12,
+            BayerRG12,
+            BayerGB12,
+            BayerBG12,
+            BayerGR12Packed,
+            BayerRG12Packed,
+            BayerGB12Packed,
+            BayerBG12Packed,
+            BayerGR10p,
+            BayerRG10p,
+            BayerGB10p,
+            BayerBG10p,
+            BayerGR12p,
+            BayerRG12p,
+            BayerGB12p,
+            BayerBG12p,
+            BayerGR16,
+            BayerRG16,
+            BayerGB16,
+            BayerBG16,
+            Rgb8,
+            Bgr8,
+            Rgb10,
+            Bgr10,
+            Rgb12,
+            Bgr12,
+            Rgb14,
+            Bgr14,
+            Rgb16,
+            Bgr16,
+            Rgba8,
+            Bgra8,
+            Rgba10,
+            Bgra10,
+            Rgba12,
+            Bgra12,
+            Rgba14,
+            Bgra14,
+            Rgba16,
+            Bgra16,
+            Yuv411,
+            Yuv422,
+            Yuv444,
+            YCbCr411_8_CbYYCrYY,
+            YCbCr422_8_CbYCrY,
+            YCbCr8_CbYCr
+        ];
+
+        for format in formats {
+            assert!(format.to_string().parse::<super::PixelFormat>() == Ok(format));
+        }
+
+        assert!("RGB8Packed".parse::<super::PixelFormat>() == Ok(Rgb8));
+        assert!("NotAFormat".parse::<super::PixelFormat>().is_err());
+    }
+
+    #[test]
+    fn bayer_patterns() {
+        use super::BayerPattern;
+
+        assert_eq!(BayerGR8.bayer_pattern(), Some(BayerPattern::GR));
+        assert_eq!(BayerRG12Packed.bayer_pattern(), Some(BayerPattern::RG));
+        assert_eq!(BayerGB10p.bayer_pattern(), Some(BayerPattern::GB));
+        assert_eq!(BayerBG16.bayer_pattern(), Some(BayerPattern::BG));
+        assert_eq!(Mono8.bayer_pattern(), None);
+        assert_eq!(Rgb8.bayer_pattern(), None);
+
+        assert!(BayerBG12p.is_bayer());
+        assert!(!Mono12p.is_bayer());
+    }
+
+    #[test]
+    fn debayer_phases() {
+        // The same 2x2 tile in each pattern, with R = 200, B = 20 and the greens 100
+        // and 60 in raster order. Every pixel sees the whole tile, so red and blue
+        // sites get the mean green of 80, and green sites keep their own.
+        let green_first = [[200, 100, 20], [200, 80, 20], [200, 80, 20], [200, 60, 20]];
+        let green_second = [[200, 80, 20], [200, 100, 20], [200, 60, 20], [200, 80, 20]];
+        let cases = [
+            (BayerGR8, [100, 200, 20, 60], green_first),
+            (BayerRG8, [200, 100, 60, 20], green_second),
+            (BayerGB8, [100, 20, 200, 60], green_first),
+            (BayerBG8, [20, 100, 60, 200], green_second)
+        ];
+
+        for (format, raw, expected) in cases {
+            let rgb = format.debayer_to_rgb8(&raw, 2, 2).unwrap();
+
+            assert_eq!(rgb, expected.concat(), "{format}");
+        }
+    }
+
+    #[test]
+    fn debayer_flat_colour() {
+        // A flat colour stays flat everywhere, edges included, whatever the phase
+        for format in [BayerGR8, BayerRG8, BayerGB8, BayerBG8] {
+            let (red_x, red_y) = format.bayer_pattern().unwrap().red_position();
+            let raw: Vec<u8> = (0..16).map(|i| match ((i % 4) & 1, (i / 4) & 1) {
+                site if site == (red_x, red_y) => 200,
+                site if site == (red_x ^ 1, red_y ^ 1) => 20,
+                _ => 100
+            }).collect();
+
+            let rgb = format.debayer_to_rgb8(&raw, 4, 4).unwrap();
+
+            assert_eq!(rgb, [200, 100, 20].repeat(16), "{format}");
+        }
+
+        assert_eq!(BayerRG12.debayer_to_rgb8(&[0; 32], 4, 4), None);
+        assert_eq!(BayerRG8.debayer_to_rgb8(&[0; 15], 4, 4), None);
+        assert_eq!(Mono8.debayer_to_rgb8(&[0; 16], 4, 4), None);
+    }
+
+    #[test]
+    fn yuv_layouts() {
+        // With neutral chroma, each luma sample comes out as grey, in pixel order
+        let yuv422 = [128, 10, 128, 20, 128, 30, 128, 40];
+        let yuv411 = [128, 10, 20, 128, 30, 40];
+        let grey = [10, 10, 10, 20, 20, 20, 30, 30, 30, 40, 40, 40].to_vec();
+
+        assert_eq!(Yuv422.yuv_to_rgb8(&yuv422, 4, 1), Some(grey.clone()));
+        assert_eq!(YCbCr422_8_CbYCrY.yuv_to_rgb8(&yuv422, 4, 1), Some(grey.clone()));
+        assert_eq!(Yuv411.yuv_to_rgb8(&yuv411, 4, 1), Some(grey.clone()));
+        assert_eq!(YCbCr411_8_CbYYCrYY.yuv_to_rgb8(&yuv411, 4, 1), Some(grey));
+
+        // Widths that don't fill whole groups, short buffers, and other formats
+        assert_eq!(Yuv422.yuv_to_rgb8(&yuv422, 3, 1), None);
+        assert_eq!(Yuv411.yuv_to_rgb8(&yuv411, 4, 2), None);
+        assert_eq!(Mono8.yuv_to_rgb8(&yuv422, 4, 1), None);
+    }
+
+    #[test]
+    fn yuv_conversion() {
+        // Cr is 50 above neutral, so R = Y + 1.402*50 and G = Y - 0.714136*50
+        let rgb = Yuv422.yuv_to_rgb8(&[128, 100, 178, 150], 2, 1);
+
+        assert_eq!(rgb, Some(vec![170, 64, 100, 220, 114, 150]));
+
+        // Cb is 100 below neutral, so G = Y + 0.344136*100 and B = Y - 1.772*100
+        let rgb = Yuv411.yuv_to_rgb8(&[28, 200, 200, 128, 200, 200], 4, 1);
+
+        assert_eq!(rgb, Some([200, 234, 23].repeat(4)));
+
+        // The chroma between groups is interpolated, here with Cr going from 0 to 50
+        // above neutral, and the last group has nothing after it to interpolate to
+        let raw = [128, 100, 128, 100, 128, 100, 178, 100];
+        let rgb = Yuv422.yuv_to_rgb8(&raw, 4, 1).unwrap();
+
+        let expected = [[100, 100, 100], [135, 82, 100], [170, 64, 100], [170, 64, 100]];
+
+        assert_eq!(rgb, expected.concat());
+    }
 }
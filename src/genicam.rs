@@ -0,0 +1,163 @@
+use crate::error::Error;
+use crate::Result;
+
+
+
+// GigE cameras have the location of their GenICam XML in their bootstrap registers,
+// as a URL in a fixed-size string
+pub const FIRST_URL_ADDRESS: u64 = 0x0200;
+pub const URL_LENGTH: usize = 512;
+
+// Far bigger than any real feature XML, zipped or not, but it stops a bad length
+// from the camera turning into a huge allocation
+pub const MAX_XML_SIZE: usize = 16*1024*1024;
+
+const ZIP_LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const ZIP_LOCAL_HEADER_SIZE: usize = 30;
+const ZIP_FLAG_DATA_DESCRIPTOR: u16 = 0x08;
+
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct LocalUrl {
+    pub file_name: String,
+    pub address: u64,
+    pub length: usize
+}
+
+// The URLs look like "Local:name.zip;8000;1A2B", with the address and length in
+// hex, and maybe with a query like "?SchemaVersion=1.1.0" on the end. Only local
+// URLs, for XML in the camera's own memory, are supported.
+pub fn parse_local_url(url: &str) -> Result<LocalUrl> {
+    let url = url.split('?').next().unwrap_or(url);
+    let location = url.strip_prefix("Local:")
+                      .or_else(|| url.strip_prefix("local:"))
+                      .ok_or(Error::NotSupported)?;
+    let mut parts = location.split(';');
+
+    let (file_name, address, length) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(file_name), Some(address), Some(length)) => (file_name, address, length),
+        _ => return Err(Error::InvalidValue)
+    };
+
+    let hex = |s: &str| {
+        let s = s.trim();
+        let s = s.strip_prefix("0x").unwrap_or(s);
+
+        u64::from_str_radix(s, 16).map_err(|_| Error::InvalidValue)
+    };
+
+    let length = hex(length)?;
+
+    if length > MAX_XML_SIZE as u64 { return Err(Error::InvalidValue) }
+
+    Ok(LocalUrl {
+        file_name: file_name.to_string(),
+        address: hex(address)?,
+        length: length as usize
+    })
+}
+
+// The XML is either stored as it is or in a zip file of its own
+pub fn decode_xml(file_name: &str, data: &[u8]) -> Result<String> {
+    let mut xml = if file_name.to_lowercase().ends_with(".zip") { unzip_first(data)? }
+                  else { data.to_vec() };
+
+    // It can be padded with zeros to fill the memory it's in
+    let end = xml.iter().position(|&b| b == 0).unwrap_or(xml.len());
+
+    xml.truncate(end);
+
+    String::from_utf8(xml).map_err(|_| Error::BadString)
+}
+
+// Only the first file is wanted, so this reads its local header and ignores the
+// central directory at the end of the zip entirely
+fn unzip_first(zip: &[u8]) -> Result<Vec<u8>> {
+    if zip.len() < ZIP_LOCAL_HEADER_SIZE { return Err(Error::InvalidValue) }
+
+    let u16_at = |i: usize| u16::from_le_bytes([zip[i], zip[i + 1]]);
+    let u32_at = |i: usize| u32::from_le_bytes([zip[i], zip[i + 1], zip[i + 2], zip[i + 3]]);
+
+    if u32_at(0) != ZIP_LOCAL_HEADER_SIGNATURE { return Err(Error::InvalidValue) }
+
+    let flags = u16_at(6);
+    let method = u16_at(8);
+    let compressed_size = u32_at(18) as usize;
+    let start = ZIP_LOCAL_HEADER_SIZE + u16_at(26) as usize + u16_at(28) as usize;
+    let data = zip.get(start..).ok_or(Error::InvalidValue)?;
+
+    // With a data descriptor, the sizes come after the data rather than in the
+    // header. Inflating finds the end of the data by itself, so that's fine unless
+    // the data is stored uncompressed.
+    let has_descriptor = flags & ZIP_FLAG_DATA_DESCRIPTOR != 0;
+    let data = if has_descriptor { data }
+               else { data.get(..compressed_size).ok_or(Error::InvalidValue)? };
+
+    match (method, has_descriptor) {
+        (0, false) => Ok(data.to_vec()),
+        (8, _) => miniz_oxide::inflate::decompress_to_vec_with_limit(data, MAX_XML_SIZE)
+                      .map_err(|_| Error::InvalidValue),
+        _ => Err(Error::NotSupported)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zip_with(method: u16, contents: &[u8], uncompressed_size: usize) -> Vec<u8> {
+        let name = b"camera.xml";
+        let mut zip = Vec::new();
+
+        zip.extend_from_slice(&ZIP_LOCAL_HEADER_SIGNATURE.to_le_bytes());
+        zip.extend_from_slice(&20u16.to_le_bytes());
+        zip.extend_from_slice(&0u16.to_le_bytes());
+        zip.extend_from_slice(&method.to_le_bytes());
+        zip.extend_from_slice(&[0; 8]);
+        zip.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&(uncompressed_size as u32).to_le_bytes());
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&0u16.to_le_bytes());
+        zip.extend_from_slice(name);
+        zip.extend_from_slice(contents);
+
+        zip
+    }
+
+    #[test]
+    fn local_urls() {
+        let url = parse_local_url("Local:camera.zip;8000;1A2B?SchemaVersion=1.1.0");
+
+        assert_eq!(url, Ok(LocalUrl {
+            file_name: "camera.zip".to_string(),
+            address: 0x8000,
+            length: 0x1A2B
+        }));
+
+        assert_eq!(parse_local_url("local:camera.xml;0x100;0x20").map(|u| u.length), Ok(0x20));
+        assert_eq!(parse_local_url("http://example.com/camera.xml"), Err(Error::NotSupported));
+        assert_eq!(parse_local_url("Local:camera.xml;100"), Err(Error::InvalidValue));
+        assert_eq!(parse_local_url("Local:camera.xml;100;xyz"), Err(Error::InvalidValue));
+        assert_eq!(parse_local_url("Local:big.xml;100;FFFFFFFF"), Err(Error::InvalidValue));
+    }
+
+    #[test]
+    fn plain_xml() {
+        assert_eq!(decode_xml("camera.xml", b"<xml/>\0\0\0"), Ok("<xml/>".to_string()));
+    }
+
+    #[test]
+    fn zipped_xml() {
+        let xml = b"<RegisterDescription></RegisterDescription>";
+        let stored = zip_with(0, xml, xml.len());
+        let deflated = zip_with(8, &miniz_oxide::deflate::compress_to_vec(xml, 6), xml.len());
+        let expected = Ok(String::from_utf8(xml.to_vec()).unwrap());
+
+        assert_eq!(decode_xml("camera.zip", &stored), expected);
+        assert_eq!(decode_xml("CAMERA.ZIP", &deflated), expected);
+        assert_eq!(decode_xml("camera.zip", b"not a zip"), Err(Error::InvalidValue));
+    }
+}
@@ -0,0 +1,66 @@
+use crate::camera::{Camera, Frame, StreamContinue};
+use crate::Result;
+use std::sync::mpsc;
+
+
+
+pub struct CameraGroup {
+    cameras: Vec<Camera>
+}
+
+impl CameraGroup {
+    pub fn new(cameras: Vec<Camera>) -> Self {
+        Self { cameras }
+    }
+
+    pub fn cameras(&self) -> &[Camera] {
+        &self.cameras
+    }
+
+    pub fn cameras_mut(&mut self) -> &mut [Camera] {
+        &mut self.cameras
+    }
+
+    pub fn into_cameras(self) -> Vec<Camera> {
+        self.cameras
+    }
+
+    // Starts all the cameras streaming into one channel, with each frame tagged by
+    // the index of the camera it came from. If any camera fails to start, the ones
+    // already started are stopped again.
+    pub fn start(&mut self, buffers: usize)
+    -> Result<mpsc::Receiver<(usize, Frame<Vec<u8>>)>> {
+        let (tx, rx) = mpsc::channel();
+
+        for i in 0..self.cameras.len() {
+            let tx = tx.clone();
+            let handler = move |frame: Frame<&[u8]>| {
+                let res = tx.send((i, frame.with_vec_data()));
+                StreamContinue(res.is_ok())
+            };
+
+            if let Err(e) = self.cameras[i].start_streaming(handler, buffers) {
+                for camera in &mut self.cameras[..i] {
+                    let _ = camera.stop_streaming();
+                }
+
+                return Err(e);
+            }
+        }
+
+        Ok(rx)
+    }
+
+    // Stops every camera, even if some fail to stop, and returns the first error
+    pub fn stop(&mut self) -> Result<()> {
+        let mut res = Ok(());
+
+        for camera in &mut self.cameras {
+            let cam_res = camera.stop_streaming();
+
+            if res.is_ok() { res = cam_res; }
+        }
+
+        res
+    }
+}
@@ -3,35 +3,58 @@ mod vimba_sys;
 mod vimba;
 mod error;
 mod util;
-mod format;
+#[cfg(feature = "genicam")]
+mod genicam;
 
 // Public modules
 pub mod camera;
 pub mod feature;
+pub mod format;
+pub mod group;
 
 
 
 pub use error::Error;
-pub use vimba::Vimba;
-pub use format::PixelFormat;
+pub use vimba::{Vimba, VimbaConfig, Version};
+pub use format::{PixelFormat, BayerPattern};
+pub use vimba_sys::{VmbHandle_t, VmbFrame_t};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+// Calls a Vimba function, turning its return code into a Result. Outside of this
+// crate it needs the "sys" feature, which exposes the functions to call with it.
+// With the "log" feature, every call and its outcome is logged.
 #[macro_export]
 macro_rules! vmbcall {
     ($func: ident $(, $arg: expr)*) => {
         {
-            use crate::error::error_code_to_result;
-            error_code_to_result(unsafe { $func($($arg),*) })
+            use $crate::error_code_to_result;
+            error_code_to_result(
+                unsafe { $func($($arg),*) },
+                stringify!($func),
+                stringify!($($arg),*)
+            )
         }
     }
 }
 
+#[doc(hidden)]
+pub use error::error_code_to_result;
+
+// The raw Vimba C API, for anything this crate doesn't wrap yet
+#[cfg(feature = "sys")]
+pub mod sys {
+    pub use crate::vimba_sys::*;
+}
+
 
 
 pub mod prelude {
     pub use crate::feature::HasFeatures;
     pub use crate::vimba::Vimba;
-    pub use crate::camera::{Camera, AccessMode, Frame, StreamContinue};
+    pub use crate::camera::{
+        Camera, AccessMode, AcquisitionMode, AutoMode, Frame, StreamContinue
+    };
     pub use crate::format::PixelFormat;
+    pub use crate::group::CameraGroup;
 }
@@ -1,7 +1,77 @@
+use crate::error::Error;
+use crate::Result;
 use std::ffi::CStr;
+use std::ptr;
+use std::collections::BTreeSet;
+use std::sync::{Mutex, PoisonError};
 
 
 
+const LIST_ATTEMPTS: usize = 5;
+
+
+
+// For strings Vimba always fills in. A null pointer is still caught, so that a
+// misbehaving transport layer gives a panic rather than a segfault.
 pub unsafe fn pointer_to_str<'a>(p: *const i8) -> &'a str {
+    assert!(!p.is_null(), "Vimba returned a null string pointer");
+
     CStr::from_ptr(p).to_str().expect("Vimba returned bad (non-UTF8) string data")
 }
+
+// For optional strings, which Vimba leaves either null or empty when not present
+pub unsafe fn pointer_to_option_str<'a>(p: *const i8) -> Option<&'a str> {
+    if p.is_null() { return None }
+
+    Some(pointer_to_str(p)).filter(|s| !s.is_empty())
+}
+
+// Gives a copy of the string that lives forever, for strings that would otherwise
+// only live as long as a handle. Each distinct string is only ever copied once, so
+// this is for things like enum entry names, of which there are only so many.
+pub fn intern(s: &str) -> &'static str {
+    static INTERNED: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+    let mut interned = INTERNED.lock().unwrap_or_else(PoisonError::into_inner);
+
+    match interned.get(s) {
+        Some(s) => s,
+        None => {
+            let s: &'static str = Box::leak(s.into());
+
+            interned.insert(s);
+            s
+        }
+    }
+}
+
+// Vimba's list functions get called once to count the items and again to fill them
+// in. Devices can come and go in between, in which case the fill says there's more
+// data, so count again and retry a few times. The closure gets a buffer, its length
+// and a place to put the number of items found.
+pub fn list_with_retry<T, F>(mut list: F) -> Result<Vec<T>>
+where T: Clone + Default, F: FnMut(*mut T, u32, &mut u32) -> Result<()> {
+    let mut n: u32 = 0;
+
+    list(ptr::null_mut(), 0, &mut n)?;
+
+    for _ in 0..LIST_ATTEMPTS {
+        if n == 0 { return Ok(Vec::new()) }
+
+        let mut items = vec![T::default(); n as usize];
+        let mut found: u32 = 0;
+
+        match list(items.as_mut_ptr(), n, &mut found) {
+            Ok(()) => {
+                // Fewer items than counted if any went away in the meantime
+                items.truncate(found as usize);
+
+                return Ok(items)
+            },
+            Err(Error::MoreData) => list(ptr::null_mut(), 0, &mut n)?,
+            Err(e) => return Err(e)
+        }
+    }
+
+    Err(Error::MoreData)
+}
@@ -1,17 +1,21 @@
 use crate::vimba_sys::*;
 use crate::feature::*;
 use crate::camera::*;
+use crate::error::Error;
+use crate::util::list_with_retry;
 use crate::{vmbcall, Result};
 use std::ffi::CString;
-use std::{mem, ptr};
+use std::{fmt, mem, ptr};
 use std::sync::{Arc, Mutex, Weak};
+use std::env;
+use std::path::PathBuf;
 use lazy_static::lazy_static;
 
 
 
 const VERSION_INFO_SIZE: u32 = mem::size_of::<VmbVersionInfo_t>() as u32;
-const CAMERA_INFO_SIZE: u32 = mem::size_of::<VmbCameraInfo_t>() as u32;
-const GLOBAL_HANDLE: VmbHandle_t = 1 as VmbHandle_t;
+pub(crate) const CAMERA_INFO_SIZE: u32 = mem::size_of::<VmbCameraInfo_t>() as u32;
+pub(crate) const GLOBAL_HANDLE: VmbHandle_t = 1 as VmbHandle_t;
 
 
 
@@ -37,18 +41,93 @@ lazy_static! {
 
 
 
+// Fields are in order of significance, so the derived ordering is the right one
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VimbaConfig {
+    // Directories to search for GenTL transport layers (.cti files), on top of any
+    // already in the GENICAM_GENTL32_PATH or GENICAM_GENTL64_PATH variable
+    pub transport_layer_paths: Vec<PathBuf>
+}
+
+impl VimbaConfig {
+    // Classic Vimba only finds transport layers through the environment, so the
+    // paths have to be put there before starting up. They're added after whatever
+    // the user already set, which is left as it is.
+    fn apply(&self) -> Result<()> {
+        if self.transport_layer_paths.is_empty() { return Ok(()) }
+
+        let var = if cfg!(target_pointer_width = "64") { "GENICAM_GENTL64_PATH" }
+                  else { "GENICAM_GENTL32_PATH" };
+        let mut paths: Vec<PathBuf> = env::var_os(var)
+            .map(|existing| env::split_paths(&existing).collect())
+            .unwrap_or_default();
+
+        for path in &self.transport_layer_paths {
+            if !paths.contains(path) { paths.push(path.clone()); }
+        }
+
+        let paths = env::join_paths(&paths).map_err(|_| Error::BadParameter)?;
+
+        env::set_var(var, paths);
+
+        Ok(())
+    }
+}
+
+
+
 pub struct Vimba {
     ctx: Arc<VimbaContext>
 }
 
 impl Vimba {
+    // The bindings were generated from the VimbaC headers of Vimba 6.0. Older
+    // runtimes have different struct sizes, which otherwise only show up later as
+    // StructSize errors.
+    pub const MIN_VERSION: Version = Version { major: 1, minor: 9, patch: 0 };
+
     pub fn new() -> Result<Self> {
+        Self::new_with_config(VimbaConfig::default())
+    }
+
+    // The config can only take effect when Vimba is started, so if it's already
+    // running because of another Vimba instance, any non-default config is refused.
+    // Transport layer paths are set in the environment, which isn't thread-safe, so
+    // with any of those this must be called before other threads are spawned.
+    pub fn new_with_config(config: VimbaConfig) -> Result<Self> {
         let mut ctx_weak = CONTEXT.lock().unwrap();
         
         match ctx_weak.upgrade() {
+            Some(_) if config != VimbaConfig::default() => Err(Error::InvalidCall),
             Some(ctx) => Ok(Self { ctx }),
             None => {
+                config.apply()?;
+
                 let ctx = Arc::new(VimbaContext::new()?);
+                let version = query_version()?;
+
+                if version < Self::MIN_VERSION {
+                    return Err(Error::VersionMismatch {
+                        found: version,
+                        required: Self::MIN_VERSION
+                    })
+                }
+
                 *ctx_weak = Arc::downgrade(&ctx);
 
                 Ok(Self { ctx })
@@ -56,12 +135,12 @@ impl Vimba {
         }
     }
 
-    pub fn get_version(&self) -> Result<String> {
-        let mut version = VmbVersionInfo_t::default();
-        
-        vmbcall!(VmbVersionQuery, &mut version, VERSION_INFO_SIZE)?;
+    pub fn version(&self) -> Result<Version> {
+        query_version()
+    }
 
-        Ok(format!("{}.{}.{}", version.major, version.minor, version.patch))
+    pub fn get_version(&self) -> Result<String> {
+        Ok(self.version()?.to_string())
     }
 
     pub fn get_num_cameras(&self) -> Result<usize> {
@@ -73,24 +152,58 @@ impl Vimba {
     }
 
     pub fn list_cameras(&self) -> Result<Vec<CameraInfo>> {
-        let mut n = self.get_num_cameras()? as u32;
-        let mut cameras = vec![VmbCameraInfo_t::default(); n as usize];
-
-        vmbcall!(VmbCamerasList, cameras.as_mut_ptr(), n, &mut n, CAMERA_INFO_SIZE)?;
+        let cameras = list_with_retry(|cameras, len, found| vmbcall!(
+            VmbCamerasList,
+            cameras, len, found, CAMERA_INFO_SIZE
+        ))?;
 
         Ok(cameras.into_iter().map(CameraInfo::from_c_struct).collect())
     }
 
+    // Vimba has no way to list the cameras of just one interface, so this filters
+    // the full list
+    pub fn list_cameras_on_interface(&self, interface_id: &str) -> Result<Vec<CameraInfo>> {
+        let mut cameras = self.list_cameras()?;
+
+        cameras.retain(|info| info.interface_id == interface_id);
+
+        Ok(cameras)
+    }
+
     pub fn open_camera(&self, id: &str, access_mode: AccessMode) -> Result<Camera> {
-        let id = CString::new(id).expect("id cannot have internal zeros");
+        if !access_mode.is_valid_for_open() { return Err(Error::BadParameter) }
+
+        let id_cstr = CString::new(id).expect("id cannot have internal zeros");
         let mut handle: VmbHandle_t = ptr::null_mut();
 
-        vmbcall!(VmbCameraOpen, id.as_ptr(), access_mode.bits(), &mut handle)?;
+        vmbcall!(VmbCameraOpen, id_cstr.as_ptr(), access_mode.bits(), &mut handle)?;
 
-        Ok(Camera::from_handle(handle, self.ctx.clone()))
+        Ok(Camera::from_handle(handle, id, access_mode, self.ctx.clone()))
+    }
+
+    pub fn open_camera_by_serial(&self, serial: &str, access_mode: AccessMode)
+    -> Result<Camera> {
+        let cameras = self.list_cameras()?;
+        let mut matches = cameras.iter().filter(|info| info.serial == serial);
+
+        match (matches.next(), matches.next()) {
+            (Some(info), None) => self.open_camera(&info.id, access_mode),
+            (Some(_), Some(_)) => Err(Error::DuplicateSerial),
+            (None, _) => Err(Error::NotFound)
+        }
     }
 }
 
+fn query_version() -> Result<Version> {
+    let mut version = VmbVersionInfo_t::default();
+
+    vmbcall!(VmbVersionQuery, &mut version, VERSION_INFO_SIZE)?;
+
+    Ok(Version { major: version.major, minor: version.minor, patch: version.patch })
+}
+
+
+
 impl HasFeatures for Vimba {
     fn get_feature(&self, name: &str) -> Result<FeatureValue> {
         GLOBAL_HANDLE.get_feature(name)
@@ -111,4 +224,40 @@ impl HasFeatures for Vimba {
     fn is_command_done(&self, name: &str) -> Result<bool> {
         GLOBAL_HANDLE.is_command_done(name)
     }
+
+    fn get_feature_int_range(&self, name: &str) -> Result<(i64, i64)> {
+        GLOBAL_HANDLE.get_feature_int_range(name)
+    }
+
+    fn get_feature_int_increment(&self, name: &str) -> Result<i64> {
+        GLOBAL_HANDLE.get_feature_int_increment(name)
+    }
+
+    fn get_feature_float_range(&self, name: &str) -> Result<(f64, f64)> {
+        GLOBAL_HANDLE.get_feature_float_range(name)
+    }
+
+    fn get_feature_float_increment(&self, name: &str) -> Result<Option<f64>> {
+        GLOBAL_HANDLE.get_feature_float_increment(name)
+    }
+
+    fn list_enum_entries(&self, name: &str) -> Result<Vec<String>> {
+        GLOBAL_HANDLE.list_enum_entries(name)
+    }
+
+    fn is_enum_entry_available(&self, name: &str, entry: &str) -> Result<bool> {
+        GLOBAL_HANDLE.is_enum_entry_available(name, entry)
+    }
+
+    fn feature_flags(&self, name: &str) -> Result<FeatureFlag> {
+        GLOBAL_HANDLE.feature_flags(name)
+    }
+
+    fn get_feature_raw_length(&self, name: &str) -> Result<usize> {
+        GLOBAL_HANDLE.get_feature_raw_length(name)
+    }
+
+    fn read_feature_raw_into(&self, name: &str, buf: &mut [u8]) -> Result<usize> {
+        GLOBAL_HANDLE.read_feature_raw_into(name, buf)
+    }
 }